@@ -1,7 +1,7 @@
-use aoc_2023::read_lines_from_file;
+use aoc_2023::input::read_input;
 
 fn main() {
-    let lines = read_lines_from_file("day1.input");
+    let lines = read_input(1);
     for line in &lines {
         println!("{}", number_from_line(&line));
     }
@@ -1,8 +1,9 @@
 use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
-use aoc_2023::read_lines_from_file;
+use aoc_2023::input::{flag_value, read_input};
+use render::{Backend, Renderer};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 #[derive(PartialEq)]
 enum Direction {
     North,
@@ -20,6 +21,15 @@ impl Direction {
             Direction::West => Direction::East
         }
     }
+
+    fn step(&self, pos: (i32, i32)) -> (i32, i32) {
+        match self {
+            Direction::North => (pos.0, pos.1 - 1),
+            Direction::South => (pos.0, pos.1 + 1),
+            Direction::East => (pos.0 + 1, pos.1),
+            Direction::West => (pos.0 - 1, pos.1),
+        }
+    }
 }
 #[derive(Clone, PartialEq)]
 enum Tile {
@@ -43,6 +53,11 @@ impl Tile {
         }
     }
 
+    /// Pipe connecting `first` and `second`, in the same order `Tile::new` uses.
+    fn pipe(first: Direction, second: Direction) -> Self {
+        ['|', '-', 'F', '7', 'L', 'J'].into_iter().map(Tile::new).find(|tile| {tile.connects(&first) && tile.connects(&second)}).unwrap()
+    }
+
     fn connects(&self, dir: &Direction) -> bool {
         match self {
             Tile::Ground => false,
@@ -59,6 +74,26 @@ impl Tile {
     }
 }
 
+impl Tile {
+    fn symbol(&self) -> char {
+        match self {
+            Tile::Ground => '.',
+            Tile::Start => 'S',
+            Tile::Pipe(first, second) => {
+                match (first, second) {
+                    (Direction::North, Direction::South) => '|',
+                    (Direction::East, Direction::West) => '-',
+                    (Direction::South, Direction::East) => 'F',
+                    (Direction::West, Direction::South) => '7',
+                    (Direction::East, Direction::North) => 'L',
+                    (Direction::West, Direction::North) => 'J',
+                    _ => panic!()
+                }
+            }
+        }
+    }
+}
+
 impl Debug for Tile {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -92,7 +127,7 @@ impl Debug for Map {
             for tile in line {
                 write!(f, "{:?}", tile).unwrap()
             }
-            write!(f, "\n").unwrap()
+            writeln!(f).unwrap()
         }
         Ok(())
     }
@@ -140,20 +175,70 @@ impl Map {
         }
     }
 
+    /// The pipe hidden under the start tile, inferred from its connecting neighbours. When
+    /// more than two neighbours point at the start, the pair whose path closes wins.
+    fn start_tile(&self) -> Tile {
+        let dirs = [Direction::North, Direction::South, Direction::East, Direction::West].into_iter().filter(|dir| {self.connects_to(self.start, *dir)}).collect::<Vec<_>>();
+        if let [first, second] = dirs[..] {
+            return Tile::pipe(first, second);
+        }
+
+        dirs.iter().find_map(|first| {
+            self.follow(*first).filter(|last| {last.opposite() != *first && dirs.contains(&last.opposite())}).map(|last| {Tile::pipe(*first, last.opposite())})
+        }).unwrap_or_else(|| {panic!("No loop through start, it connects to {:?}", dirs)})
+    }
+
+    /// Follows the pipes leaving the start towards `dir`, returning the direction of the
+    /// step that leads back into the start, or `None` if the path breaks off.
+    fn follow(&self, mut dir: Direction) -> Option<Direction> {
+        let mut pos = dir.step(self.start);
+        while pos != self.start {
+            dir = match self.at(pos.0, pos.1)? {
+                Tile::Pipe(first, second) if first == dir.opposite() => second,
+                Tile::Pipe(first, second) if second == dir.opposite() => first,
+                _ => return None,
+            };
+            pos = dir.step(pos);
+        }
+
+        Some(dir)
+    }
+
+    /// The main loop as an ordered list of positions, beginning at the start tile.
+    fn trace(&self) -> Vec<(i32, i32)> {
+        let mut res = vec![self.start];
+        let mut dir = match self.start_tile() {
+            Tile::Pipe(first, _) => first,
+            _ => panic!(),
+        };
+        let mut pos = dir.step(self.start);
+
+        while pos != self.start {
+            res.push(pos);
+            dir = match self.at(pos.0, pos.1) {
+                Some(Tile::Pipe(first, second)) => if first == dir.opposite() { second } else { first },
+                tile => panic!("Loop broken at {:?}: {:?}", pos, tile),
+            };
+            pos = dir.step(pos);
+        }
+
+        res
+    }
+
     fn inside(&self, path: &HashSet<(i32, i32)>, pos: (i32, i32)) -> bool {
         let mut east_count = 0;
         let mut entry_direction = None;
         for x in 0..pos.0 {
             if path.contains(&(x, pos.1)) {
-               match self.at(x, pos.1).unwrap() {
+               let tile = if (x, pos.1) == self.start { self.start_tile() } else { self.at(x, pos.1).unwrap() };
+               match tile {
                    Tile::Pipe(first, second) if first == Direction::North && second == Direction::South => east_count += 1,
                    Tile::Pipe(first, second) if first == Direction::North || first == Direction::South || second == Direction::North || second == Direction::South => {
-                       let mut dir;
-                       if first == Direction::North || second == Direction::North {
-                           dir = Direction::North;
+                       let dir = if first == Direction::North || second == Direction::North {
+                           Direction::North
                        } else {
-                           dir = Direction::South;
-                       }
+                           Direction::South
+                       };
 
                        match entry_direction {
                            Some(ref previous) => {
@@ -178,88 +263,343 @@ impl Map {
         east_count % 2 != 0
     }
 
-    fn print_inside(&self, path: &HashSet<(i32, i32)>) -> i32 {
+    fn count_inside(&self, path: &HashSet<(i32, i32)>) -> i32 {
         let mut res = 0;
         for y in 0..self.size().1 {
             for x in 0..self.size().0 {
-                match self.at(x,y) {
-                    Some(t) if path.contains(&(x, y)) => print!("\x1b[1m\x1b[31m{:?}\x1b[0m", t),
-                    Some(_) => {
-                        match self.inside(path, (x,y)) {
-                            true => {res += 1; print!("\x1b[92mX\x1b[0m")},
-                            false => print!("\x1b[37mO\x1b[0m")
-                        }
-                    }
-                    _ => panic!()
+                if !path.contains(&(x, y)) && self.inside(path, (x, y)) {
+                    res += 1;
                 }
             }
-            print!("\n")
         }
 
         res
     }
 }
-fn main() {
-    let lines = read_lines_from_file("day10.input");
-    let map = Map::new(lines);
 
-    println!("{:?}", map);
-    println!("Tile {:?}", map.start);
-    println!("North: {}", map.connects_to(map.start, Direction::North));
-    println!("South: {}", map.connects_to(map.start, Direction::South));
-    println!("East: {}", map.connects_to(map.start, Direction::East));
-    println!("West: {}", map.connects_to(map.start, Direction::West));
+mod area {
+    /// Twice the signed area enclosed by the ordered vertices, via the shoelace formula.
+    pub fn shoelace(path: &[(i32, i32)]) -> i64 {
+        path.iter().zip(path.iter().cycle().skip(1)).map(|(a, b)| {a.0 as i64 * b.1 as i64 - b.0 as i64 * a.1 as i64}).sum()
+    }
 
-    let mut result: HashSet<(i32, i32)> = HashSet::new();
+    /// Tiles strictly inside the loop, by Pick's theorem: `A = i + b / 2 - 1`.
+    pub fn enclosed(path: &[(i32, i32)]) -> i64 {
+        shoelace(path).abs() / 2 - path.len() as i64 / 2 + 1
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Region {
+        Loop,
+        Inside,
+        Outside,
+    }
 
-    let mut options: Vec<Vec<(i32, i32)>> = vec![vec![map.start]];
+    /// Classifies every tile by scanning each row and flipping parity on every loop tile
+    /// that connects north.
+    pub fn classify(path: &[(i32, i32)], size: (i32, i32)) -> Vec<Vec<Region>> {
+        let mut north: Vec<Vec<Option<bool>>> = vec![vec![None; size.0 as usize]; size.1 as usize];
+        for (idx, pos) in path.iter().enumerate() {
+            let prev = path[(idx + path.len() - 1) % path.len()];
+            let next = path[(idx + 1) % path.len()];
+            north[pos.1 as usize][pos.0 as usize] = Some(prev.1 == pos.1 - 1 || next.1 == pos.1 - 1);
+        }
 
-    loop {
-        let current = options.pop().unwrap();
-        let pos = *current.last().unwrap();
+        north.iter().map(|row| {
+            let mut inside = false;
+            row.iter().map(|tile| {
+                match tile {
+                    Some(true) => {inside = !inside; Region::Loop},
+                    Some(false) => Region::Loop,
+                    None if inside => Region::Inside,
+                    None => Region::Outside,
+                }
+            }).collect()
+        }).collect()
+    }
 
-        let mut next: Vec<(i32, i32)> = vec![];
+    /// Tiles strictly inside the loop, by scanline parity.
+    #[cfg(test)]
+    pub fn scanline(path: &[(i32, i32)], size: (i32, i32)) -> i64 {
+        classify(path, size).iter().flatten().filter(|region| {**region == Region::Inside}).count() as i64
+    }
+}
 
-        if map.connects_to(pos, Direction::North) {
-            next.push((pos.0, pos.1 - 1));
+mod flood {
+    use std::collections::VecDeque;
+    use super::area::Region;
+    use super::{Direction, Map};
+
+    const SCALE: usize = 3;
+
+    /// Classifies every tile by upscaling the loop 3x, so that gaps between adjacent pipes
+    /// become passable, and flood filling from the border.
+    pub fn classify(map: &Map, path: &[(i32, i32)]) -> Vec<Vec<Region>> {
+        let (width, height) = (map.size().0 as usize * SCALE, map.size().1 as usize * SCALE);
+        let mut walls: Vec<Vec<bool>> = vec![vec![false; width]; height];
+
+        for pos in path {
+            let tile = if *pos == map.start { map.start_tile() } else { map.at(pos.0, pos.1).unwrap() };
+            let centre = (pos.0 * SCALE as i32 + 1, pos.1 * SCALE as i32 + 1);
+            walls[centre.1 as usize][centre.0 as usize] = true;
+            for dir in [Direction::North, Direction::South, Direction::East, Direction::West] {
+                if tile.connects(&dir) {
+                    let arm = dir.step(centre);
+                    walls[arm.1 as usize][arm.0 as usize] = true;
+                }
+            }
         }
 
-        if map.connects_to(pos, Direction::South) {
-            next.push((pos.0, pos.1 + 1));
+        let mut outside: Vec<Vec<bool>> = vec![vec![false; width]; height];
+        let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
+        for x in 0..width {
+            queue.push_back((x, 0));
+            queue.push_back((x, height - 1));
+        }
+        for y in 0..height {
+            queue.push_back((0, y));
+            queue.push_back((width - 1, y));
         }
 
-        if map.connects_to(pos, Direction::East) {
-            next.push((pos.0 + 1, pos.1));
+        while let Some((x, y)) = queue.pop_front() {
+            if walls[y][x] || outside[y][x] {
+                continue;
+            }
+            outside[y][x] = true;
+
+            if x > 0 { queue.push_back((x - 1, y)) }
+            if x + 1 < width { queue.push_back((x + 1, y)) }
+            if y > 0 { queue.push_back((x, y - 1)) }
+            if y + 1 < height { queue.push_back((x, y + 1)) }
         }
 
-        if map.connects_to(pos, Direction::West) {
-            next.push((pos.0 - 1, pos.1));
-       }
+        (0..map.size().1 as usize).map(|y| {
+            (0..map.size().0 as usize).map(|x| {
+                let (cx, cy) = (x * SCALE + 1, y * SCALE + 1);
+                if walls[cy][cx] {
+                    Region::Loop
+                } else if outside[cy][cx] {
+                    Region::Outside
+                } else {
+                    Region::Inside
+                }
+            }).collect()
+        }).collect()
+    }
 
-        for next in next {
-            if !(current.len() > 1 && current.iter().rev().nth(1).unwrap() == &next) {
-                match map.at(next.0, next.1) {
-                    Some(Tile::Start) => {
-                        result =  current.iter().map(|x| {*x}).collect();
-                    },
-                    Some(_) => {
-                        let mut option = current.clone();
-                        option.push(next);
-                        options.push(option);
+    #[cfg(test)]
+    pub fn enclosed(map: &Map, path: &[(i32, i32)]) -> i64 {
+        classify(map, path).iter().flatten().filter(|region| {**region == Region::Inside}).count() as i64
+    }
+}
+
+mod render {
+    use std::io::IsTerminal;
+    use super::area::Region;
+    use super::Map;
+
+    const SCALE: usize = 8;
+
+    #[derive(Debug, PartialEq)]
+    pub enum Backend {
+        Ascii,
+        Unicode,
+        Ansi,
+        Svg,
+        Ppm,
+    }
+
+    impl From<&str> for Backend {
+        fn from(value: &str) -> Self {
+            match value {
+                "ascii" => Backend::Ascii,
+                "unicode" => Backend::Unicode,
+                "ansi" => Backend::Ansi,
+                "svg" => Backend::Svg,
+                "ppm" => Backend::Ppm,
+                _ => panic!("Cant parse {value}, expected ascii, unicode, ansi, svg or ppm"),
+            }
+        }
+    }
+
+    impl Backend {
+        pub fn is_image(&self) -> bool {
+            matches!(self, Backend::Svg | Backend::Ppm)
+        }
+
+        /// Colour output only makes sense on a terminal, fall back to plain box drawing otherwise.
+        pub fn for_stdout(self) -> Backend {
+            match self {
+                Backend::Ansi if !std::io::stdout().is_terminal() => Backend::Unicode,
+                backend => backend,
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Cell {
+        Start,
+        Region(Region),
+    }
+
+    impl Cell {
+        fn colour(&self) -> (u8, u8, u8) {
+            match self {
+                Cell::Start => (230, 200, 40),
+                Cell::Region(Region::Loop) => (200, 40, 40),
+                Cell::Region(Region::Inside) => (60, 200, 90),
+                Cell::Region(Region::Outside) => (220, 220, 220),
+            }
+        }
+    }
+
+    pub struct Renderer<'a> {
+        map: &'a Map,
+        regions: &'a [Vec<Region>],
+    }
+
+    impl<'a> Renderer<'a> {
+        pub fn new(map: &'a Map, regions: &'a [Vec<Region>]) -> Renderer<'a> {
+            Renderer {
+                map,
+                regions,
+            }
+        }
+
+        fn cell(&self, x: i32, y: i32) -> Cell {
+            if (x, y) == self.map.start {
+                Cell::Start
+            } else {
+                Cell::Region(self.regions[y as usize][x as usize])
+            }
+        }
+
+        pub fn render(&self, backend: &Backend) -> String {
+            match backend {
+                Backend::Ascii | Backend::Unicode | Backend::Ansi => self.text(backend),
+                Backend::Svg => self.svg(),
+                Backend::Ppm => self.ppm(),
+            }
+        }
+
+        fn text(&self, backend: &Backend) -> String {
+            let mut res = String::new();
+            for y in 0..self.map.size().1 {
+                for x in 0..self.map.size().0 {
+                    let tile = self.map.at(x, y).unwrap();
+                    let glyph = match (self.cell(x, y), backend) {
+                        (Cell::Start, _) => String::from("S"),
+                        (Cell::Region(Region::Loop), Backend::Ascii) => tile.symbol().to_string(),
+                        (Cell::Region(Region::Loop), _) => format!("{:?}", tile),
+                        (Cell::Region(Region::Inside), _) => String::from("I"),
+                        (Cell::Region(Region::Outside), _) => String::from("O"),
+                    };
+                    match (self.cell(x, y), backend) {
+                        (Cell::Start, Backend::Ansi) => res.push_str(&format!("\x1b[1m\x1b[93m{glyph}\x1b[0m")),
+                        (Cell::Region(Region::Loop), Backend::Ansi) => res.push_str(&format!("\x1b[1m\x1b[31m{glyph}\x1b[0m")),
+                        (Cell::Region(Region::Inside), Backend::Ansi) => res.push_str(&format!("\x1b[92m{glyph}\x1b[0m")),
+                        (Cell::Region(Region::Outside), Backend::Ansi) => res.push_str(&format!("\x1b[37m{glyph}\x1b[0m")),
+                        _ => res.push_str(&glyph),
                     }
+                }
+                res.push('\n');
+            }
+
+            res
+        }
 
-                    None => panic!()
+        fn svg(&self) -> String {
+            let (width, height) = self.map.size();
+            let mut res = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n", width as usize * SCALE, height as usize * SCALE);
+            for y in 0..height {
+                for x in 0..width {
+                    let (r, g, b) = self.cell(x, y).colour();
+                    res.push_str(&format!("  <rect x=\"{}\" y=\"{}\" width=\"{SCALE}\" height=\"{SCALE}\" fill=\"rgb({r},{g},{b})\"/>\n", x as usize * SCALE, y as usize * SCALE));
                 }
             }
+            let points = self.map.trace().iter().map(|(x, y)| {format!("{},{}", *x as usize * SCALE + SCALE / 2, *y as usize * SCALE + SCALE / 2)}).collect::<Vec<_>>().join(" ");
+            res.push_str(&format!("  <polygon points=\"{points}\" fill=\"none\" stroke=\"black\" stroke-width=\"1\"/>\n"));
+            res.push_str("</svg>\n");
+
+            res
         }
 
-        if !result.is_empty() {
-            break;
+        fn ppm(&self) -> String {
+            let (width, height) = self.map.size();
+            let mut res = format!("P3\n{} {}\n255\n", width as usize * SCALE, height as usize * SCALE);
+            for y in 0..height as usize * SCALE {
+                let row = (0..width as usize * SCALE).map(|x| {
+                    let (r, g, b) = self.cell((x / SCALE) as i32, (y / SCALE) as i32).colour();
+                    format!("{r} {g} {b}")
+                }).collect::<Vec<_>>();
+                res.push_str(&row.join(" "));
+                res.push('\n');
+            }
+
+            res
         }
     }
-    println!("{:?}", result.len() / 2);
+}
+
+fn main() {
+    let backend = flag_value("--render").map(|value| {Backend::from(value.as_str())}).unwrap_or(Backend::Ansi);
+    let engine = flag_value("--engine");
+
+    let lines = read_input(10);
+    let map = Map::new(lines);
+    let path = map.trace();
+    let regions = match engine.as_deref() {
+        Some("flood") => flood::classify(&map, &path),
+        Some("scanline") | None => area::classify(&path, map.size()),
+        Some(engine) => panic!("Cant parse {engine}, expected scanline or flood"),
+    };
+
+    // Image formats are meant to be redirected to a file, so print nothing else
+    if backend.is_image() {
+        print!("{}", Renderer::new(&map, &regions).render(&backend));
+        return;
+    }
+
+    println!("{:?}", map);
+    println!("Tile {:?}", map.start);
+    println!("North: {}", map.connects_to(map.start, Direction::North));
+    println!("South: {}", map.connects_to(map.start, Direction::South));
+    println!("East: {}", map.connects_to(map.start, Direction::East));
+    println!("West: {}", map.connects_to(map.start, Direction::West));
+
+    println!("Start tile: {:?}", map.start_tile());
+    println!("{:?}", path.len() / 2);
+
+    print!("{}", Renderer::new(&map, &regions).render(&backend.for_stdout()));
 
-    let res = map.print_inside(&result);
-    println!("Res: {}", res);
+    let result = path.iter().copied().collect::<HashSet<_>>();
+    println!("Ray cast: {}", map.count_inside(&result));
 
-}
\ No newline at end of file
+    println!("Res: {}", area::enclosed(&path));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc_2023::input::{resolve, Source};
+    use aoc_2023::read_lines_from_file;
+
+    #[test]
+    fn engines_agree_on_examples() {
+        for example in 1..=5 {
+            let map = Map::new(read_lines_from_file(resolve(10, &Source::Example(example))));
+            let path = map.trace();
+            let ray_cast = map.count_inside(&path.iter().copied().collect()) as i64;
+            assert_eq!(area::enclosed(&path), area::scanline(&path, map.size()), "day10.example.{example}");
+            assert_eq!(area::enclosed(&path), flood::enclosed(&map, &path), "day10.example.{example}");
+            assert_eq!(area::enclosed(&path), ray_cast, "day10.example.{example}");
+        }
+    }
+
+    #[test]
+    fn start_with_extra_neighbours() {
+        let map = Map::new([".....", ".F-7.", "-S.|.", ".L-J.", "....."].map(String::from).to_vec());
+        assert_eq!(map.start_tile(), Tile::pipe(Direction::North, Direction::South));
+        assert_eq!(area::enclosed(&map.trace()), 1);
+    }
+}
@@ -1,6 +1,6 @@
-use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
-use aoc_2023::read_lines_from_file;
+use aoc_2023::input::{flag_value, read_input};
+use aoc_2023::math::MathError;
 
 #[derive(PartialEq, Clone)]
 enum Tile {
@@ -29,7 +29,7 @@ impl Debug for Tile {
 
 struct Image {
     map: Vec<Vec<Tile>>,
-    galaxies: Vec<(i32, i32)>,
+    galaxies: Vec<(i64, i64)>,
 }
 
 impl Debug for Image {
@@ -49,14 +49,14 @@ impl Debug for Image {
 
 impl Image {
     fn new(input: Vec<String>) -> Image {
-        let mut galaxies: Vec<(i32, i32)> = vec![];
+        let mut galaxies: Vec<(i64, i64)> = vec![];
         let mut map: Vec<Vec<Tile>> = vec![];
-        for y in 0..input.len() {
+        for (y, row) in input.iter().enumerate() {
             let mut line: Vec<Tile> = vec![];
-            for x in 0..input[y].len() {
-                let cur = Tile::new(input[y].chars().nth(x).unwrap());
+            for (x, input) in row.chars().enumerate() {
+                let cur = Tile::new(input);
                 if cur == Tile::Galaxy {
-                    galaxies.push((x as i32, y as i32));
+                    galaxies.push((x as i64, y as i64));
                 }
                 line.push(cur);
             }
@@ -81,75 +81,220 @@ impl Image {
         }
     }
 
-    fn expanded_galaxies(&self, expansion: i32) -> Vec<(i32,i32)> {
-        let mut empty_rows: HashSet<i32> = HashSet::new();
-        for y in 0..self.size().1 {
-            let mut empty = true;
-            for x in 0..self.size().0{
-                match self.at(x, y) {
-                    Some(Tile::Galaxy) => {empty = false; break;},
-                    _ => {}
-                }
-            }
-            if empty {
-                empty_rows.insert(y);
+    /// Shifts of each row and column: the number of expanding lines before it, scaled by
+    /// the axis factor.
+    fn offsets(&self, counts: &[usize], length: usize, expansion: &Expansion) -> Result<Vec<i64>, MathError> {
+        let step = expansion.factor.checked_sub(1).ok_or(MathError::Overflow)?;
+        let mut res: Vec<i64> = vec![];
+        let mut offset: i64 = 0;
+        for count in counts {
+            res.push(offset);
+            if expansion.expands(*count, length) {
+                offset = offset.checked_add(step).ok_or(MathError::Overflow)?;
             }
         }
 
-        let mut empty_cols: HashSet<i32> = HashSet::new();
-        for x in 0..self.size().0 {
-            let mut empty = true;
-            for y in 0..self.size().1{
-                match self.at(x, y) {
-                    Some(Tile::Galaxy) => {empty = false; break;},
-                    _ => {}
-                }
-            }
-            if empty {
-                empty_cols.insert(x);
-            }
+        Ok(res)
+    }
+
+    fn expanded_galaxies(&self, policy: &Policy) -> Result<Vec<(i64, i64)>, MathError> {
+        let (width, height) = (self.size().0 as usize, self.size().1 as usize);
+        let mut row_counts = vec![0; height];
+        let mut col_counts = vec![0; width];
+        for (x, y) in &self.galaxies {
+            row_counts[*y as usize] += 1;
+            col_counts[*x as usize] += 1;
         }
 
-        let mut res: Vec<(i32, i32)> = vec![];
-        let mut expanded_rows = 0;
+        let rows = self.offsets(&row_counts, width, &policy.rows)?;
+        let cols = self.offsets(&col_counts, height, &policy.cols)?;
 
-        for y in 0..self.size().1 {
-            let mut expanded_cols = 0;
-            for x in 0..self.size().0 {
-                if empty_cols.contains(&x) {
-                    expanded_cols += 1;
-                }
-                match self.at(x, y) {
-                    Some(Tile::Galaxy) => res.push((x + (expanded_cols * (expansion - 1)), y + (expanded_rows * (expansion - 1)))),
-                    _ => {}
+        self.galaxies.iter().map(|(x, y)| {
+            Ok((x.checked_add(cols[*x as usize]).ok_or(MathError::Overflow)?, y.checked_add(rows[*y as usize]).ok_or(MathError::Overflow)?))
+        }).collect()
+    }
+
+    /// Sum of distances over all pairs of galaxies, without visiting every pair.
+    fn total(&self, policy: &Policy, metric: Metric) -> Result<u128, MathError> {
+        let galaxies = self.expanded_galaxies(policy)?;
+        let xs = galaxies.iter().map(|galaxy| {galaxy.0 as i128});
+        let ys = galaxies.iter().map(|galaxy| {galaxy.1 as i128});
+
+        let (first, second) = match metric {
+            Metric::Manhattan => (axis_distance(xs.collect())?, axis_distance(ys.collect())?),
+            // Chebyshev distance is half the Manhattan distance in coordinates rotated by 45 degrees
+            Metric::Chebyshev => (axis_distance(xs.clone().zip(ys.clone()).map(|(x, y)| {x + y}).collect())?, axis_distance(xs.zip(ys).map(|(x, y)| {x - y}).collect())?),
+            Metric::EuclideanSquared => (axis_squares(xs.collect())?, axis_squares(ys.collect())?),
+        };
+        let sum = first.checked_add(second).ok_or(MathError::Overflow)?;
+
+        match metric {
+            Metric::Chebyshev => Ok(sum / 2),
+            _ => Ok(sum),
+        }
+    }
+
+    fn closest_pair(&self, policy: &Policy, metric: Metric) -> Result<Option<(usize, usize, u128)>, MathError> {
+        let galaxies = self.expanded_galaxies(policy)?;
+        let mut res: Option<(usize, usize, u128)> = None;
+        for index in 0..galaxies.len() {
+            if let Some((other, distance)) = nearest(&galaxies, metric, index)? {
+                if res.is_none_or(|(_, _, best)| {distance < best}) {
+                    res = Some((index, other, distance));
                 }
             }
-            if empty_rows.contains(&y) {
-                expanded_rows += 1;
-            }
         }
 
-        res
+        Ok(res)
+    }
+
+    fn farthest_pair(&self, policy: &Policy, metric: Metric) -> Result<Option<(usize, usize, u128)>, MathError> {
+        let galaxies = self.expanded_galaxies(policy)?;
+        let mut pairs = (0..galaxies.len()).flat_map(|first| {(first + 1..galaxies.len()).map(move |second| {(first, second)})});
+        pairs.try_fold(None, |best: Option<(usize, usize, u128)>, (first, second)| {
+            let distance = metric.distance(galaxies[first], galaxies[second])?;
+            Ok(match best {
+                Some((_, _, farthest)) if farthest > distance => best,
+                _ => Some((first, second, distance)),
+            })
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Expansion {
+    factor: i64,
+    /// Lines whose share of galaxy tiles is at most this expand; 0 expands only empty lines.
+    density: f64,
+}
+
+impl Expansion {
+    fn expands(&self, galaxies: usize, length: usize) -> bool {
+        galaxies as f64 <= self.density * length as f64
     }
 }
 
-fn distance(first: (i32, i32), second: (i32, i32)) -> i32 {
-    (first.0 - second.0).abs() + (first.1 - second.1).abs()
+#[derive(Clone, Copy, Debug)]
+struct Policy {
+    rows: Expansion,
+    cols: Expansion,
+}
+
+impl Policy {
+    fn uniform(factor: i64) -> Policy {
+        let expansion = Expansion {
+            factor,
+            density: 0.0,
+        };
+
+        Policy {
+            rows: expansion,
+            cols: expansion,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Metric {
+    Manhattan,
+    Chebyshev,
+    EuclideanSquared,
+}
+
+impl From<&str> for Metric {
+    fn from(value: &str) -> Self {
+        match value {
+            "manhattan" => Metric::Manhattan,
+            "chebyshev" => Metric::Chebyshev,
+            "euclidean" => Metric::EuclideanSquared,
+            _ => panic!("Cant parse {value}, expected manhattan, chebyshev or euclidean"),
+        }
+    }
+}
+
+impl Metric {
+    fn distance(&self, first: (i64, i64), second: (i64, i64)) -> Result<u128, MathError> {
+        let dx = (first.0 as i128 - second.0 as i128).unsigned_abs();
+        let dy = (first.1 as i128 - second.1 as i128).unsigned_abs();
+
+        match self {
+            Metric::Manhattan => Ok(dx + dy),
+            Metric::Chebyshev => Ok(dx.max(dy)),
+            Metric::EuclideanSquared => dx.checked_mul(dx).zip(dy.checked_mul(dy)).and_then(|(x, y)| {x.checked_add(y)}).ok_or(MathError::Overflow),
+        }
+    }
+}
+
+/// Closest other galaxy to the galaxy at `index`, with its distance.
+fn nearest(galaxies: &[(i64, i64)], metric: Metric, index: usize) -> Result<Option<(usize, u128)>, MathError> {
+    (0..galaxies.len()).filter(|other| {*other != index}).try_fold(None, |best: Option<(usize, u128)>, other| {
+        let distance = metric.distance(galaxies[index], galaxies[other])?;
+        Ok(match best {
+            Some((_, nearest)) if nearest <= distance => best,
+            _ => Some((other, distance)),
+        })
+    })
+}
+
+/// Sum of `|a - b|` over all pairs: after sorting, the i-th coordinate is subtracted from
+/// by the i coordinates before it, which a running prefix sum provides.
+fn axis_distance(mut coordinates: Vec<i128>) -> Result<u128, MathError> {
+    coordinates.sort_unstable();
+
+    let mut res: i128 = 0;
+    let mut prefix: i128 = 0;
+    for (idx, coordinate) in coordinates.iter().enumerate() {
+        res = coordinate.checked_mul(idx as i128).and_then(|x| {x.checked_sub(prefix)}).and_then(|x| {res.checked_add(x)}).ok_or(MathError::Overflow)?;
+        prefix = prefix.checked_add(*coordinate).ok_or(MathError::Overflow)?;
+    }
+
+    Ok(res as u128)
+}
+
+/// Sum of `(a - b)^2` over all pairs, which expands to `n * sum(a^2) - sum(a)^2`.
+fn axis_squares(coordinates: Vec<i128>) -> Result<u128, MathError> {
+    let overflow = |x: Option<i128>| {x.ok_or(MathError::Overflow)};
+    let sum = overflow(coordinates.iter().try_fold(0i128, |acc, x| {acc.checked_add(*x)}))?;
+    let squares = overflow(coordinates.iter().try_fold(0i128, |acc, x| {x.checked_mul(*x).and_then(|square| {acc.checked_add(square)})}))?;
+
+    let res = overflow((coordinates.len() as i128).checked_mul(squares))?.checked_sub(overflow(sum.checked_mul(sum))?);
+    Ok(overflow(res)? as u128)
 }
 
 fn main() {
-    let lines = read_lines_from_file("day11.input");
+    let lines = read_input(11);
     let image = Image::new(lines);
     println!("{image:?}");
 
-    let mut distances: Vec<i64> = vec![];
-    let galaxies = image.expanded_galaxies(1_000_000);
+    println!("Part 1: {}", image.total(&Policy::uniform(2), Metric::Manhattan).unwrap());
+    println!("Part 2: {}", image.total(&Policy::uniform(1_000_000), Metric::Manhattan).unwrap());
 
-    for first in 0..galaxies.len() {
-        for second in first + 1..galaxies.len() {
-            distances.push(distance(galaxies[first], galaxies[second]) as i64);
+    let factor = |name: &str| {flag_value(name).map(|value| {value.parse::<i64>().unwrap_or_else(|_| {panic!("Cant parse {name} {value}")})})};
+
+    if ["--factor", "--rows", "--cols", "--density", "--metric"].iter().any(|name| {flag_value(name).is_some()}) {
+        let uniform = factor("--factor").unwrap_or(2);
+        let density = flag_value("--density").map(|value| {value.parse::<f64>().unwrap_or_else(|_| {panic!("Cant parse --density {value}")})}).unwrap_or(0.0);
+        let policy = Policy {
+            rows: Expansion { factor: factor("--rows").unwrap_or(uniform), density },
+            cols: Expansion { factor: factor("--cols").unwrap_or(uniform), density },
+        };
+        let metric = flag_value("--metric").map(|value| {Metric::from(value.as_str())}).unwrap_or(Metric::Manhattan);
+
+        println!("{:?} {:?}", policy, metric);
+        let report = image.total(&policy, metric).and_then(|total| {
+            Ok((total, image.closest_pair(&policy, metric)?, image.farthest_pair(&policy, metric)?))
+        });
+        match report {
+            Ok((total, closest, farthest)) => {
+                println!("Total: {}", total);
+                if let Some((first, second, distance)) = closest {
+                    println!("Closest: {:?} {:?} {}", image.galaxies[first], image.galaxies[second], distance);
+                }
+                if let Some((first, second, distance)) = farthest {
+                    println!("Farthest: {:?} {:?} {}", image.galaxies[first], image.galaxies[second], distance);
+                }
+            }
+            Err(err) => println!("Cant expand: {err}"),
         }
     }
-
-    println!("{}", distances.iter().sum::<i64>())
-}
\ No newline at end of file
+}
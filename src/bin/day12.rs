@@ -1,6 +1,7 @@
-use std::collections::HashMap;
-use std::fmt::{Debug, Formatter};
-use aoc_2023::read_lines_from_file;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
+use aoc_2023::input::{flag_value, read_input};
 
 #[derive(PartialEq, Clone, Eq, Hash)]
 enum Condition {
@@ -46,218 +47,716 @@ impl Debug for Row {
 }
 
 impl Row {
-    fn new(input: &String) -> Row {
+    fn new(input: &str) -> Row {
         Row {
             springs: input[0..input.find(' ').unwrap()].chars().map(Condition::new).collect(),
             groups: input[input.find(' ').unwrap()+1..].split(',').filter_map(|x| {x.parse::<i32>().ok()}).collect(),
         }
     }
 
-    fn contiguous_groups(&self) -> Vec<i32> {
-        let mut res: Vec<i32> = vec![];
-        let mut counter = 0;
-        for spring in &self.springs {
-            match spring {
-                Condition::Damaged => counter += 1,
-                _ => {
-                    if counter != 0 {
-                        res.push(counter);
-                        counter = 0;
-                    }
+    /// The row repeated `times` times, springs joined by an unknown spring.
+    fn unfold(&self, times: usize) -> Row {
+        let mut springs: Vec<Condition> = vec![];
+        for idx in 0..times {
+            if idx > 0 {
+                springs.push(Condition::Unknown);
+            }
+            springs.extend(self.springs.iter().cloned());
+        }
+
+        Row {
+            springs,
+            groups: self.groups.repeat(times),
+        }
+    }
+
+    /// Suffix counts by dynamic programming: `counts[i][j]` is the number of ways
+    /// `springs[i..]` can hold exactly `groups[j..]`.
+    fn counts(&self) -> Counts {
+        let springs = self.springs.len();
+        let groups = self.groups.len();
+
+        // operational[i]: number of operational springs in springs[..i]
+        let mut operational: Vec<usize> = vec![0; springs + 1];
+        for (idx, spring) in self.springs.iter().enumerate() {
+            operational[idx + 1] = operational[idx] + (spring == &Condition::Operational) as usize;
+        }
+
+        let mut counts = Counts {
+            width: groups + 1,
+            table: vec![Count::zero(); (springs + 2) * (groups + 1)],
+            operational,
+        };
+        counts.set(springs, groups, Count::one());
+        counts.set(springs + 1, groups, Count::one());
+
+        for i in (0..springs).rev() {
+            for j in (0..=groups).rev() {
+                let mut res = Count::zero();
+
+                if self.springs[i] != Condition::Damaged {
+                    res = res.add(counts.at(i + 1, j));
+                }
+
+                if let Some(end) = self.group_end(&counts, i, j) {
+                    res = res.add(counts.at(end + 1, j + 1));
                 }
+
+                counts.set(i, j, res);
             }
         }
-        if counter != 0 {
-            res.push(counter);
+
+        counts
+    }
+
+    /// End of group `j` if it can start at spring `i`, followed by a spring that isn't damaged.
+    fn group_end(&self, counts: &Counts, i: usize, j: usize) -> Option<usize> {
+        if self.springs[i] == Condition::Operational || j >= self.groups.len() {
+            return None;
         }
 
-        res
+        let end = i + self.groups[j] as usize;
+        if end <= self.springs.len() && counts.operational[end] == counts.operational[i] && (end == self.springs.len() || self.springs[end] != Condition::Damaged) {
+            Some(end)
+        } else {
+            None
+        }
     }
 
-    fn possible(&self) -> bool {
-        let mut res: Vec<i32> = vec![];
-        let mut counter = 0;
-        let mut idx = 0;
-        for spring in &self.springs {
-            match spring {
-                Condition::Damaged => counter += 1,
-                Condition::Operational => {
-                    if counter != 0 {
-                        if idx < self.groups.len() && counter == self.groups[idx] {
-                            res.push(counter);
-                            idx += 1;
-                            counter = 0;
-                        } else {
-                            return false;
-                        }
-                    }
-                }
-                Condition::Unknown => {
-                    if counter != 0 {
-                        res.push(counter);
-                        counter = 0;
+    fn arrangements(&self) -> Count {
+        self.counts().at(0, 0).clone()
+    }
+
+    /// The `index`-th valid collapsed arrangement, ordering operational before damaged at
+    /// every unknown spring. Skips whole subtrees using their arrangement counts.
+    fn arrangement(&self, counts: &Counts, index: &Count) -> Option<Row> {
+        if index >= counts.at(0, 0) {
+            return None;
+        }
+
+        let mut springs: Vec<Condition> = vec![];
+        let (mut i, mut j) = (0, 0);
+        let mut index = index.clone();
+
+        while i < self.springs.len() {
+            if self.springs[i] != Condition::Damaged {
+                match index.checked_sub(counts.at(i + 1, j)) {
+                    None => {
+                        springs.push(Condition::Operational);
+                        i += 1;
+                        continue;
                     }
-                    break;
+                    Some(rest) => index = rest,
                 }
             }
+
+            let end = self.group_end(counts, i, j).unwrap();
+            springs.extend((i..end).map(|_| {Condition::Damaged}));
+            if end < self.springs.len() {
+                springs.push(Condition::Operational);
+            }
+            i = end + 1;
+            j += 1;
         }
-        if counter != 0 {
-            if idx < self.groups.len() && counter == self.groups[idx] {
-                res.push(counter);
-                idx += 1;
-            } else {
-                return false;
+
+        Some(Row {
+            springs,
+            groups: self.groups.clone(),
+        })
+    }
+
+    fn iter(&self) -> Arrangements<'_> {
+        let counts = self.counts();
+        Arrangements {
+            row: self,
+            total: counts.at(0, 0).clone(),
+            counts,
+            index: Count::zero(),
+        }
+    }
+
+    /// A uniformly chosen valid arrangement.
+    fn sample(&self, counts: &Counts, random: &mut Random) -> Option<Row> {
+        match counts.at(0, 0) {
+            total if total.is_zero() => None,
+            total => self.arrangement(counts, &random.below(total)),
+        }
+    }
+}
+
+/// Unsigned integer of any size, as arrangement counts of unfolded rows grow exponentially
+/// with the unfold factor. Values fitting 128 bits are kept inline so that ordinary rows
+/// count without allocating; larger ones as little-endian limbs without trailing zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Count {
+    Small(u128),
+    Big(Vec<u64>),
+}
+
+impl Default for Count {
+    fn default() -> Self {
+        Count::Small(0)
+    }
+}
+
+impl Count {
+    fn zero() -> Count {
+        Count::Small(0)
+    }
+
+    fn one() -> Count {
+        Count::Small(1)
+    }
+
+    fn is_zero(&self) -> bool {
+        *self == Count::Small(0)
+    }
+
+    fn limbs(&self) -> Vec<u64> {
+        match self {
+            Count::Small(value) => vec![*value as u64, (value >> 64) as u64],
+            Count::Big(limbs) => limbs.clone(),
+        }
+    }
+
+    fn from_limbs(mut limbs: Vec<u64>) -> Count {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+
+        match limbs.len() {
+            0..=2 => Count::Small(limbs.iter().rev().fold(0, |acc, limb| {acc << 64 | *limb as u128})),
+            _ => Count::Big(limbs),
+        }
+    }
+
+    fn bits(&self) -> usize {
+        match self {
+            Count::Small(value) => 128 - value.leading_zeros() as usize,
+            Count::Big(limbs) => limbs.len() * 64 - limbs[limbs.len() - 1].leading_zeros() as usize,
+        }
+    }
+
+    fn add(&self, rhs: &Count) -> Count {
+        if let (Count::Small(first), Count::Small(second)) = (self, rhs) {
+            if let Some(sum) = first.checked_add(*second) {
+                return Count::Small(sum);
             }
         }
 
-        let unknowns = self.springs.iter().filter(|x| {*x == &Condition::Unknown}).count();
+        let (first, second) = (self.limbs(), rhs.limbs());
+        let mut limbs: Vec<u64> = vec![];
+        let mut carry = false;
+        for idx in 0..first.len().max(second.len()) {
+            let (sum, overflow) = first.get(idx).copied().unwrap_or(0).overflowing_add(second.get(idx).copied().unwrap_or(0));
+            let (sum, carried) = sum.overflowing_add(carry as u64);
+            limbs.push(sum);
+            carry = overflow || carried;
+        }
+        limbs.push(carry as u64);
+
+        Count::from_limbs(limbs)
+    }
 
-        if idx == self.groups.len() {
-            return true;
-        } else if idx > self.groups.len() {
-            false
-        } else {
-            unknowns != 0
+    /// `self - rhs`, or `None` if `rhs` is larger.
+    fn checked_sub(&self, rhs: &Count) -> Option<Count> {
+        if let (Count::Small(first), Count::Small(second)) = (self, rhs) {
+            return first.checked_sub(*second).map(Count::Small);
+        }
+        if *self < *rhs {
+            return None;
         }
+
+        let (first, second) = (self.limbs(), rhs.limbs());
+        let mut limbs: Vec<u64> = vec![];
+        let mut borrow = false;
+        for (idx, limb) in first.iter().enumerate() {
+            let (difference, overflow) = limb.overflowing_sub(second.get(idx).copied().unwrap_or(0));
+            let (difference, borrowed) = difference.overflowing_sub(borrow as u64);
+            limbs.push(difference);
+            borrow = overflow || borrowed;
+        }
+
+        Some(Count::from_limbs(limbs))
     }
 
-    fn collapsed(&self) -> bool {
-       !self.springs.iter().any(|x| {x == &Condition::Unknown})
+    /// `self * factor + term`, used for parsing.
+    fn mul_add(&self, factor: u64, term: u64) -> Count {
+        if let Count::Small(value) = self {
+            if let Some(res) = value.checked_mul(factor as u128).and_then(|x| {x.checked_add(term as u128)}) {
+                return Count::Small(res);
+            }
+        }
+
+        let mut limbs: Vec<u64> = vec![];
+        let mut carry = term as u128;
+        for limb in self.limbs() {
+            let product = limb as u128 * factor as u128 + carry;
+            limbs.push(product as u64);
+            carry = product >> 64;
+        }
+        limbs.push(carry as u64);
+
+        Count::from_limbs(limbs)
     }
 
-    fn valid(&self) -> bool {
-        let actual = self.contiguous_groups();
-        self.collapsed() && actual == self.groups
+    /// `(self / divisor, self % divisor)`, used for printing.
+    fn div_rem(&self, divisor: u64) -> (Count, u64) {
+        if let Count::Small(value) = self {
+            return (Count::Small(value / divisor as u128), (value % divisor as u128) as u64);
+        }
+
+        let mut limbs = self.limbs();
+        let mut remainder: u128 = 0;
+        for limb in limbs.iter_mut().rev() {
+            let current = remainder << 64 | *limb as u128;
+            *limb = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+
+        (Count::from_limbs(limbs), remainder as u64)
     }
 
-    fn remainder(&self) -> Row {
-        let mut res: Vec<i32> = vec![];
-        let mut counter = 0;
-        let mut start = 0;
-        let mut idx = 0;
-        for spring in &self.springs {
-            match spring {
-                Condition::Damaged => counter += 1,
-                Condition::Operational => {
-                    if counter != 0 {
-                        if counter == self.groups[idx]{
-                            start += counter;
-                            idx += 1;
+    fn to_u128(&self) -> Option<u128> {
+        match self {
+            Count::Small(value) => Some(*value),
+            Count::Big(_) => None,
+        }
+    }
+}
+
+impl From<u128> for Count {
+    fn from(value: u128) -> Self {
+        Count::Small(value)
+    }
+}
+
+impl Ord for Count {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Count::Small(first), Count::Small(second)) => first.cmp(second),
+            (Count::Small(_), Count::Big(_)) => Ordering::Less,
+            (Count::Big(_), Count::Small(_)) => Ordering::Greater,
+            (Count::Big(first), Count::Big(second)) => first.len().cmp(&second.len()).then_with(|| {first.iter().rev().cmp(second.iter().rev())}),
+        }
+    }
+}
+
+impl PartialOrd for Count {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Count {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+        let mut chunks: Vec<u64> = vec![];
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            let (quotient, remainder) = rest.div_rem(CHUNK);
+            chunks.push(remainder);
+            rest = quotient;
+        }
+
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((first, others)) => {
+                write!(f, "{first}")?;
+                others.iter().rev().try_for_each(|chunk| {write!(f, "{chunk:019}")})
+            }
+        }
+    }
+}
+
+impl FromStr for Count {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(String::from("empty number"));
+        }
+
+        s.chars().try_fold(Count::zero(), |acc, c| {
+            c.to_digit(10).map(|digit| {acc.mul_add(10, digit as u64)}).ok_or_else(|| {format!("invalid digit {c}")})
+        })
+    }
+}
+
+impl std::iter::Sum for Count {
+    fn sum<I: Iterator<Item = Count>>(iter: I) -> Self {
+        iter.fold(Count::zero(), |acc, x| {acc.add(&x)})
+    }
+}
+
+struct Counts {
+    width: usize,
+    table: Vec<Count>,
+    operational: Vec<usize>,
+}
+
+impl Counts {
+    fn at(&self, i: usize, j: usize) -> &Count {
+        &self.table[i * self.width + j]
+    }
+
+    fn set(&mut self, i: usize, j: usize, value: Count) {
+        self.table[i * self.width + j] = value;
+    }
+}
+
+/// Lazily yields every valid collapsed arrangement of a row, in order.
+struct Arrangements<'a> {
+    row: &'a Row,
+    counts: Counts,
+    index: Count,
+    total: Count,
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.row.arrangement(&self.counts, &self.index);
+        self.index = self.index.add(&Count::one()).min(self.total.clone());
+        res
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.total.checked_sub(&self.index).unwrap_or_default().to_u128().and_then(|x| {usize::try_from(x).ok()});
+        (remaining.unwrap_or(usize::MAX), remaining)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.index = self.index.add(&Count::from(n as u128)).min(self.total.clone());
+        self.next()
+    }
+}
+
+/// xorshift64* generator, good enough to pick arrangements.
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        Random {
+            state: seed.max(1),
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform value in `0..bound`, drawing as many bits as `bound` has and rejecting draws
+    /// that are too large, which happens less than half the time.
+    fn below(&mut self, bound: &Count) -> Count {
+        let bits = bound.bits();
+        loop {
+            let mut limbs = (0..bits.div_ceil(64)).map(|_| {self.next()}).collect::<Vec<_>>();
+            if let Some(top) = limbs.last_mut() {
+                *top &= u64::MAX >> ((64 - bits % 64) % 64);
+            }
+
+            let value = Count::from_limbs(limbs);
+            if value < *bound {
+                return value;
+            }
+        }
+    }
+}
+
+fn main() {
+    let lines = read_input(12);
+    let rows = lines.iter().map(|line| {Row::new(line)}).collect::<Vec<_>>();
+
+    let unfold = flag_value("--unfold").map(|value| {value.parse::<usize>().unwrap_or_else(|_| {panic!("Cant parse {value}")})}).unwrap_or(5);
+
+    let number = |name: &str| {flag_value(name).map(|value| {value.parse::<u64>().unwrap_or_else(|_| {panic!("Cant parse {value}")})})};
+    if let Some(inspect) = number("--inspect") {
+        let row = rows[inspect as usize].unfold(unfold);
+        let counts = row.counts();
+        println!("{:?} variants: {}", row, counts.at(0, 0));
+
+        for arrangement in row.iter().take(number("--list").unwrap_or(10) as usize) {
+            println!("{:?}", arrangement);
+        }
+        if let Some(value) = flag_value("--nth") {
+            let nth = value.parse::<Count>().unwrap_or_else(|_| {panic!("Cant parse {value}")});
+            println!("#{nth}: {:?}", row.arrangement(&counts, &nth));
+        }
+        let seed = number("--seed").unwrap_or_else(|| {std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_nanos() as u64});
+        let mut random = Random::new(seed);
+        for _ in 0..number("--sample").unwrap_or(0) {
+            println!("Sample: {:?}", row.sample(&counts, &mut random).unwrap());
+        }
+        return;
+    }
+
+    println!("Folded: {}", rows.iter().map(Row::arrangements).sum::<Count>());
+
+    let mut res = Count::zero();
+    for row in rows.iter().map(|row| {row.unfold(unfold)}) {
+        let variants = row.arrangements();
+        res = res.add(&variants);
+        println!("{:?} variants: {}", row, variants);
+    }
+
+    println!("Unfolded x{unfold}: {}", res);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use aoc_2023::input::{resolve, Source};
+    use aoc_2023::read_lines_from_file;
+    use super::*;
+
+    /// The original search over partially collapsed rows, kept as an oracle for the counter.
+    impl Row {
+        fn contiguous_groups(&self) -> Vec<i32> {
+            let mut res: Vec<i32> = vec![];
+            let mut counter = 0;
+            for spring in &self.springs {
+                match spring {
+                    Condition::Damaged => counter += 1,
+                    _ => {
+                        if counter != 0 {
                             res.push(counter);
                             counter = 0;
-                        } else {
-                            counter = 0;
-                            break;
                         }
                     }
-
-                    start += 1;
                 }
-                Condition::Unknown => {
-                    if counter != 0 {
-                        counter = 0;
+            }
+            if counter != 0 {
+                res.push(counter);
+            }
+
+            res
+        }
+
+        fn possible(&self) -> bool {
+            let mut res: Vec<i32> = vec![];
+            let mut counter = 0;
+            let mut idx = 0;
+            for spring in &self.springs {
+                match spring {
+                    Condition::Damaged => counter += 1,
+                    Condition::Operational => {
+                        if counter != 0 {
+                            if idx < self.groups.len() && counter == self.groups[idx] {
+                                res.push(counter);
+                                idx += 1;
+                                counter = 0;
+                            } else {
+                                return false;
+                            }
+                        }
+                    }
+                    Condition::Unknown => {
+                        if counter != 0 {
+                            res.push(counter);
+                            counter = 0;
+                        }
+                        break;
                     }
-                    break;
                 }
             }
-        }
+            if counter != 0 {
+                if idx < self.groups.len() && counter == self.groups[idx] {
+                    res.push(counter);
+                    idx += 1;
+                } else {
+                    return false;
+                }
+            }
 
-        if counter != 0 {
-            if counter == self.groups[idx]{
-                start += counter;
-                res.push(counter);
+            let unknowns = self.springs.iter().filter(|x| {*x == &Condition::Unknown}).count();
+
+            if idx == self.groups.len() {
+                true
+            } else if idx > self.groups.len() {
+                false
+            } else {
+                unknowns != 0
             }
         }
 
-        Row{
-            springs: self.springs[start as usize..].to_owned(),
-            groups: self.groups[res.len()..].to_owned()
+        fn collapsed(&self) -> bool {
+           !self.springs.iter().any(|x| {x == &Condition::Unknown})
         }
-    }
 
-    fn variants(&self) -> Vec<Row> {
-        if self.collapsed() {
-            vec![self.clone()]
-        } else {
-            let mut res: Vec<Row> = vec![];
-            for index in 0..self.springs.len() {
-                match self.springs[index] {
-                    Condition::Unknown => {
-                        let mut variant1 = self.clone();
+        fn valid(&self) -> bool {
+            let actual = self.contiguous_groups();
+            self.collapsed() && actual == self.groups
+        }
 
-                        variant1.springs[index] = Condition::Damaged;
-                        if variant1.possible() {
-                            res.push(variant1);
+        fn remainder(&self) -> Row {
+            let mut res: Vec<i32> = vec![];
+            let mut counter = 0;
+            let mut start = 0;
+            let mut idx = 0;
+            for spring in &self.springs {
+                match spring {
+                    Condition::Damaged => counter += 1,
+                    Condition::Operational => {
+                        if counter != 0 {
+                            if counter == self.groups[idx]{
+                                start += counter;
+                                idx += 1;
+                                res.push(counter);
+                                counter = 0;
+                            } else {
+                                counter = 0;
+                                break;
+                            }
                         }
 
-                        let mut variant2 = self.clone();
-                        variant2.springs[index] = Condition::Operational;
-                        if variant2.possible() {
-                            res.push(variant2);
+                        start += 1;
+                    }
+                    Condition::Unknown => {
+                        if counter != 0 {
+                            counter = 0;
                         }
-
                         break;
                     }
-
-                    _ => {}
                 }
             }
 
-            res
+            if counter != 0 && counter == self.groups[idx] {
+                start += counter;
+                res.push(counter);
+            }
+
+            Row{
+                springs: self.springs[start as usize..].to_owned(),
+                groups: self.groups[res.len()..].to_owned()
+            }
         }
-    }
 
-    fn possible_variants(&self) -> i64{
-        let mut stack: Vec<Row> = Vec::new();
-        stack.push(self.clone());
-        let mut visited: HashMap<Row, i64> = HashMap::new();
+        fn variants(&self) -> Vec<Row> {
+            if self.collapsed() {
+                vec![self.clone()]
+            } else {
+                let mut res: Vec<Row> = vec![];
+                if let Some(index) = self.springs.iter().position(|x| {x == &Condition::Unknown}) {
+                    let mut variant1 = self.clone();
 
-        loop {
-            if stack.is_empty() {
-                break;
-            }
-            let cur = stack.pop().unwrap();
+                    variant1.springs[index] = Condition::Damaged;
+                    if variant1.possible() {
+                        res.push(variant1);
+                    }
+
+                    let mut variant2 = self.clone();
+                    variant2.springs[index] = Condition::Operational;
+                    if variant2.possible() {
+                        res.push(variant2);
+                    }
+                }
 
-            if visited.contains_key(&cur) {
-                continue;
+                res
             }
+        }
 
+        fn possible_variants(&self) -> i64{
+            let mut stack: Vec<Row> = Vec::new();
+            stack.push(self.clone());
+            let mut visited: HashMap<Row, i64> = HashMap::new();
 
-            if cur.valid() {
-                visited.insert(cur.to_owned(), 1);
-            } else if cur.collapsed() {
-                visited.insert(cur.to_owned(), 0);
-            } else {
-                let variants = cur.remainder().variants();
-                if variants.iter().all(|row| {visited.contains_key(row)}) {
-                    let score = variants.iter().map(|row| {visited[row]}).sum::<i64>();
-                    visited.insert(cur.to_owned(), score);
+            loop {
+                if stack.is_empty() {
+                    break;
+                }
+                let cur = stack.pop().unwrap();
+
+                if visited.contains_key(&cur) {
+                    continue;
+                }
+
+
+                if cur.valid() {
+                    visited.insert(cur.to_owned(), 1);
+                } else if cur.collapsed() {
+                    visited.insert(cur.to_owned(), 0);
                 } else {
-                    stack.push(cur.clone());
-                    stack.extend(variants.iter().filter(|row| {!visited.contains_key(row)}).map(|x| {x.to_owned()}));
+                    let variants = cur.remainder().variants();
+                    if variants.iter().all(|row| {visited.contains_key(row)}) {
+                        let score = variants.iter().map(|row| {visited[row]}).sum::<i64>();
+                        visited.insert(cur.to_owned(), score);
+                    } else {
+                        stack.push(cur.clone());
+                        stack.extend(variants.iter().filter(|row| {!visited.contains_key(row)}).map(|x| {x.to_owned()}));
+                    }
                 }
             }
+
+            visited[self]
+        }
+    }
+
+    fn rows() -> Vec<Row> {
+        let mut rows = read_lines_from_file(resolve(12, &Source::Example(1))).iter().map(|line| {Row::new(line)}).collect::<Vec<_>>();
+        rows.extend(["? 1", "# 1", ". 1", "?? 1", "#?#?# 1,1,1", "??.## 1,2", "?#?#?#?#?#?#?#? 1,3,1,6"].map(Row::new));
+        rows
+    }
+
+    #[test]
+    fn counter_matches_search() {
+        for row in rows() {
+            assert_eq!(row.arrangements(), Count::from(row.possible_variants() as u128), "{:?}", row);
+            let unfolded = row.unfold(2);
+            assert_eq!(unfolded.arrangements(), Count::from(unfolded.possible_variants() as u128), "{:?}", unfolded);
         }
+    }
 
-        visited[self]
+    #[test]
+    fn arrangements_are_valid_and_distinct() {
+        for row in rows() {
+            let arrangements = row.iter().collect::<Vec<_>>();
+            assert_eq!(Count::from(arrangements.len() as u128), row.arrangements(), "{:?}", row);
+            assert!(arrangements.iter().all(Row::valid), "{:?}", row);
+            assert!(arrangements.windows(2).all(|pair| {pair[0] != pair[1]}), "{:?}", row);
+        }
     }
-}
-fn main() {
-    let lines = read_lines_from_file("day12.input");
-    let rows = lines.iter().map(|line| {
-        let splits = line.split(' ').collect::<Vec<_>>();
-        let springs = [splits[0]; 5].join("?");
-        let groups = [splits[1]; 5].join(",");
-
-        format!("{springs} {groups}")
-    }).map(|x| {Row::new(&x)}).collect::<Vec<_>>();
-
-    let mut res = 0;
-    for row in rows {
-        let variants = row.possible_variants();
-        res += variants;
-        println!("{:?} variants: {}", row, variants);
+
+    #[test]
+    fn counts_beyond_u128() {
+        let row = Row::new("?###???????? 3,2,1");
+        assert_eq!(row.unfold(5).arrangements().to_string(), "506250");
+        assert_eq!(row.unfold(5).arrangements(), Count::Small(506250));
+
+        let wide = Row::new("?? 1").unfold(100);
+        let count = wide.arrangements();
+        assert_eq!(count.to_u128(), None);
+        assert_eq!(count.to_string(), "90548514656103281165404177077484163874504589675413336841320");
+        assert_eq!(count.to_string().parse::<Count>(), Ok(count.clone()));
+        assert_eq!(row.unfold(40).arrangements().to_string(), "73715548806266747615154599770903587341308593750");
+
+        let last = count.checked_sub(&Count::one()).unwrap();
+        assert!(wide.arrangement(&wide.counts(), &last).unwrap().valid());
+        assert_eq!(wide.arrangement(&wide.counts(), &count), None);
     }
 
-    println!("{}", res);
-}
\ No newline at end of file
+    #[test]
+    fn count_arithmetic() {
+        let max = Count::from(u128::MAX);
+        let big = max.add(&Count::one());
+        assert!(matches!(big, Count::Big(_)));
+        assert_eq!(big.to_string(), "340282366920938463463374607431768211456");
+        assert_eq!(big.bits(), 129);
+        assert_eq!(big.checked_sub(&Count::one()), Some(max.clone()));
+        assert_eq!(max.checked_sub(&big), None);
+        assert!(max < big);
+        assert_eq!("340282366920938463463374607431768211456".parse::<Count>(), Ok(big));
+        assert_eq!(Count::zero().to_string(), "0");
+        assert!("12a".parse::<Count>().is_err());
+
+        let mut random = Random::new(7);
+        let bound = Count::from(1u128 << 100).add(&Count::from(3));
+        assert!((0..100).all(|_| {random.below(&bound) < bound}));
+    }
+}
@@ -1,17 +1,62 @@
-use aoc_2023::read_lines_from_file;
+use aoc_2023::input::{flag_values, read_input};
 
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 enum Reflection {
     Horizontal(i32),
     Vertical(i32),
+    /// Mirror along the main diagonal of a square pattern.
+    Diagonal,
+    /// Mirror along the anti-diagonal of a square pattern.
+    AntiDiagonal,
+    /// Unchanged by a 180° rotation.
+    Rotational,
 }
 
-impl Reflection {
-    fn value(&self) -> i32 {
-        match self {
-            Reflection::Horizontal(i) => i * 100,
-            Reflection::Vertical(i) => i * 1,
+/// Points per reflection kind; mirror lines are multiplied by their position.
+#[derive(Debug)]
+struct Scoring {
+    horizontal: i32,
+    vertical: i32,
+    diagonal: i32,
+    anti_diagonal: i32,
+    rotational: i32,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring {
+            horizontal: 100,
+            vertical: 1,
+            diagonal: 0,
+            anti_diagonal: 0,
+            rotational: 0,
+        }
+    }
+}
+
+impl Scoring {
+    fn value(&self, reflection: &Reflection) -> i32 {
+        match reflection {
+            Reflection::Horizontal(i) => i * self.horizontal,
+            Reflection::Vertical(i) => i * self.vertical,
+            Reflection::Diagonal => self.diagonal,
+            Reflection::AntiDiagonal => self.anti_diagonal,
+            Reflection::Rotational => self.rotational,
+        }
+    }
+
+    /// Applies a `kind=points` rule.
+    fn set(&mut self, rule: &str) {
+        let (kind, points) = rule.split_once('=').unwrap_or_else(|| {panic!("Cant parse {rule}, expected kind=points")});
+        let points = points.parse().unwrap_or_else(|_| {panic!("Cant parse {points}")});
+        match kind {
+            "horizontal" => self.horizontal = points,
+            "vertical" => self.vertical = points,
+            "diagonal" => self.diagonal = points,
+            "anti-diagonal" => self.anti_diagonal = points,
+            "rotational" => self.rotational = points,
+            _ => panic!("Cant parse {kind}"),
         }
     }
 }
@@ -39,139 +84,210 @@ impl Tile {
     }
 }
 
-#[derive(Debug)]
-struct Pattern {
-    rows: Vec<Vec<Tile>>,
+/// Bitset over the cells of a row or column, of any length.
+#[derive(Debug, PartialEq, Clone)]
+struct Line {
+    words: Vec<u64>,
 }
 
-impl Pattern {
-    fn new(input: &[String]) -> Pattern {
-        Pattern {
-            rows: input.iter().map(|x| {x.chars().map(Tile::new).collect()}).collect()
+impl Line {
+    fn new(bits: usize) -> Line {
+        Line {
+            words: vec![0; bits.div_ceil(64)],
         }
     }
 
-    fn size(&self) -> (usize, usize) {
-        (self.rows[0].len(), self.rows.len())
+    fn get(&self, bit: usize) -> bool {
+        self.words[bit / 64] & 1 << (bit % 64) != 0
     }
 
-    fn row(&self, index: usize) -> Option<Vec<Tile>> {
-        if index < 1 || index > self.size().1 {
-            None
-        } else {
-            Some(self.rows[index - 1].clone())
-        }
+    fn set(&mut self, bit: usize) {
+        self.words[bit / 64] |= 1 << (bit % 64);
     }
 
-    fn col(&self, index: usize) -> Option<Vec<Tile>> {
-        if index < 1 || index > self.size().0 {
-            None
-        } else {
-            let mut res: Vec<Tile> = vec![];
-            for idx in 0..self.size().1 {
-                res.push(self.rows[idx][index - 1])
-            }
-            Some(res)
+    fn xor(&self, other: &Line) -> Line {
+        Line {
+            words: self.words.iter().zip(&other.words).map(|(first, second)| {first ^ second}).collect(),
         }
     }
 
-    fn horizontal_reflections(&self) -> Vec<usize> {
-        let mut res = vec![];
-        for split in 1..self.size().1 {
-            let mut matches = true;
-            for dist in 0..split {
-                let top = self.row(split-dist);
-                let bot = self.row(split+dist + 1);
-                if !Pattern::reflects(&top, &bot) {
-                    matches = false;
-                    break;
-                }
-            }
+    fn count_ones(&self) -> u32 {
+        self.words.iter().map(|word| {word.count_ones()}).sum()
+    }
 
-            if matches {
-                res.push(split);
-            }
-        }
+    fn first_one(&self) -> Option<usize> {
+        self.words.iter().position(|word| {*word != 0}).map(|idx| {idx * 64 + self.words[idx].trailing_zeros() as usize})
+    }
 
+    /// The first `bits` bits in reverse order.
+    fn reversed(&self, bits: usize) -> Line {
+        let mut res = Line::new(bits);
+        for bit in (0..bits).filter(|bit| {self.get(*bit)}) {
+            res.set(bits - 1 - bit);
+        }
         res
     }
+}
 
-    fn vertical_reflections(&self) -> Vec<usize> {
-        let mut res = vec![];
-        for split in 1..self.size().0 {
-            let mut matches = true;
-            for dist in 0..split {
-                let left = self.col(split-dist);
-                let right = self.col(split+dist + 1);
-                if !Pattern::reflects(&left, &right) {
-                    matches = false;
-                    break;
-                }
-            }
+#[derive(Debug)]
+struct Pattern {
+    rows: Vec<Vec<Tile>>,
+    /// Bit `x` of `row_masks[y]` and bit `y` of `col_masks[x]` are set for rocks.
+    row_masks: Vec<Line>,
+    col_masks: Vec<Line>,
+}
+
+impl Pattern {
+    fn new(input: &[String]) -> Pattern {
+        Pattern::from_rows(input.iter().map(|x| {x.chars().map(Tile::new).collect()}).collect())
+    }
 
-            if matches {
-                res.push(split);
+    fn from_rows(rows: Vec<Vec<Tile>>) -> Pattern {
+        let (width, height) = (rows.iter().map(Vec::len).max().unwrap_or(0), rows.len());
+        let mut row_masks: Vec<Line> = vec![Line::new(width); height];
+        let mut col_masks: Vec<Line> = vec![Line::new(height); width];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                if tile == &Tile::Rock {
+                    row_masks[y].set(x);
+                    col_masks[x].set(y);
+                }
             }
         }
 
-        res
+        Pattern {
+            rows,
+            row_masks,
+            col_masks,
+        }
     }
 
-    fn reflects(first: &Option<Vec<Tile>>, second: &Option<Vec<Tile>>) -> bool {
-        match first {
-            None => true,
-            Some(first) => {
-                match second {
-                    None => true,
-                    Some(second) => {
-                        for idx in 0..first.len() {
-                            if first[idx] != second[idx] {
-                                return false;
-                            }
-                        }
-
-                        true
-                    }
+    /// Mirror lines between `lines[split - 1]` and `lines[split]` where the mirrored halves
+    /// differ in exactly `smudges` cells.
+    fn mirrors(lines: &[Line], smudges: u32) -> Vec<usize> {
+        (1..lines.len()).filter(|split| {
+            let mut mismatches = 0;
+            for (first, second) in lines[..*split].iter().rev().zip(&lines[*split..]) {
+                mismatches += first.xor(second).count_ones();
+                if mismatches > smudges {
+                    return false;
                 }
             }
-        }
+
+            mismatches == smudges
+        }).collect()
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (self.col_masks.len(), self.row_masks.len())
+    }
+
+    fn reflections_with(&self, smudges: u32) -> Vec<Reflection> {
+        Pattern::mirrors(&self.row_masks, smudges).iter().map(|x| {Reflection::Horizontal(*x as i32)})
+            .chain(Pattern::mirrors(&self.col_masks, smudges).iter().map(|x| {Reflection::Vertical(*x as i32)}))
+            .collect()
     }
 
     fn reflections(&self) -> Vec<Reflection> {
-        self.horizontal_reflections().iter().map(|x| {Reflection::Horizontal(*x as i32)}).chain(self.vertical_reflections().iter().map(|x| {Reflection::Vertical(*x as i32)})).collect()
+        self.reflections_with(0)
+    }
+
+    /// Diagonal and rotational symmetries whose images differ in exactly `smudges` cells.
+    fn symmetries_with(&self, smudges: u32) -> Vec<Reflection> {
+        let (width, height) = self.size();
+        let candidates = if width == height {
+            vec![Reflection::Diagonal, Reflection::AntiDiagonal, Reflection::Rotational]
+        } else {
+            vec![Reflection::Rotational]
+        };
+
+        // every mismatching cell is seen once from each side
+        candidates.into_iter().filter(|symmetry| {
+            self.differences(symmetry).iter().map(|(_, diff)| {diff.count_ones()}).sum::<u32>() == smudges * 2
+        }).collect()
+    }
+
+    fn symmetries(&self) -> Vec<Reflection> {
+        self.symmetries_with(0)
+    }
+
+    /// Per-line differences between the pattern and its image under `reflection`, as
+    /// `(line, mask)` pairs. Lines are columns for vertical mirrors and rows otherwise.
+    fn differences(&self, reflection: &Reflection) -> Vec<(usize, Line)> {
+        let (width, height) = self.size();
+        let mirrored = |lines: &[Line], split: usize| {
+            (0..split).rev().zip(split..lines.len()).map(|(first, second)| {(first, lines[first].xor(&lines[second]))}).collect::<Vec<_>>()
+        };
+
+        match reflection {
+            Reflection::Horizontal(split) => mirrored(&self.row_masks, *split as usize),
+            Reflection::Vertical(split) => mirrored(&self.col_masks, *split as usize),
+            Reflection::Diagonal => (0..height).map(|y| {(y, self.row_masks[y].xor(&self.col_masks[y]))}).collect(),
+            Reflection::AntiDiagonal => (0..height).map(|y| {(y, self.row_masks[y].xor(&self.col_masks[height - 1 - y].reversed(height)))}).collect(),
+            Reflection::Rotational => (0..height).map(|y| {(y, self.row_masks[y].xor(&self.row_masks[height - 1 - y].reversed(width)))}).collect(),
+        }
+    }
+
+    /// The first cell differing from its image under `reflection`, as 1-based `(x, y)`.
+    fn smudge(&self, reflection: &Reflection) -> (usize, usize) {
+        let (line, offset) = self.differences(reflection).iter().find_map(|(line, diff)| {diff.first_one().map(|offset| {(*line, offset)})}).unwrap();
+
+        match reflection {
+            Reflection::Vertical(_) => (line + 1, offset + 1),
+            _ => (offset + 1, line + 1),
+        }
     }
 
     fn repaired_at(&self, x: usize, y: usize) -> Pattern {
         let mut rows = self.rows.to_owned();
         rows[y - 1][x - 1] = rows[y - 1][x - 1].flipped();
-        Pattern {
-            rows
-        }
+        Pattern::from_rows(rows)
     }
 
     fn with_alternate_reflection(&self) -> Option<(Pattern, Reflection)> {
-        let current = self.reflections();
-        for y in 1..=self.size().1 {
-            for x in 1..=self.size().0 {
-                let res = self.repaired_at(x, y);
-                let reflections = res.reflections();
-                if reflections != current && reflections.iter().any(|reflection| {!current.contains(reflection)}) {
-                    let alternate = reflections.iter().filter(|reflection| {!current.contains(reflection)}).last().unwrap().to_owned();
-                    return Some((res, alternate));
-                }
-            }
-        }
+        self.repair().map(|repair| {(repair.pattern, repair.reflection)})
+    }
 
-        None
+    fn repair(&self) -> Option<Repair> {
+        let reflection = *self.reflections_with(1).last()?;
+        let smudge = self.smudge(&reflection);
+        Some(Repair {
+            smudge,
+            reflection,
+            pattern: self.repaired_at(smudge.0, smudge.1),
+        })
     }
 }
 
+/// A pattern with its smudge at `smudge` fixed, gaining the mirror line `reflection`.
+struct Repair {
+    smudge: (usize, usize),
+    reflection: Reflection,
+    pattern: Pattern,
+}
 
 fn main() {
-    let lines = read_lines_from_file("day13.input");
-    let patterns = lines.split(|x| {x.is_empty()}).map(Pattern::new).collect::<Vec<_>>();
-    println!("Res: {}", patterns.iter().flat_map(Pattern::reflections).map(|reflection| {reflection.value()}).sum::<i32>());
+    let lines = read_input(13);
+    let patterns = lines.split(|x| {x.is_empty()}).filter(|block| {!block.is_empty()}).map(Pattern::new).collect::<Vec<_>>();
+
+    let mut scoring = Scoring::default();
+    for value in flag_values("--score") {
+        scoring.set(&value);
+    }
+
+    for (idx, pattern) in patterns.iter().enumerate() {
+        let before = pattern.reflections().into_iter().chain(pattern.symmetries()).collect::<Vec<_>>();
+        match pattern.repair() {
+            Some(repair) => {
+                let after = repair.pattern.reflections().into_iter().chain(repair.pattern.symmetries()).collect::<Vec<_>>();
+                println!("Pattern {}: {:?} smudge at {:?} gives {:?}, now {:?}", idx + 1, before, repair.smudge, repair.reflection, after);
+            }
+            None => println!("Pattern {}: {:?} no smudge found", idx + 1, before),
+        }
+    }
+
+    println!("Res: {}", patterns.iter().flat_map(|pattern| {pattern.reflections().into_iter().chain(pattern.symmetries())}).map(|reflection| {scoring.value(&reflection)}).sum::<i32>());
 
     let alternates = patterns.iter().flat_map(Pattern::with_alternate_reflection).map(|(_, reflection)| {reflection}).collect::<Vec<_>>();
-    println!("Res 2: {}", alternates.iter().map(|reflection| {reflection.value()}).sum::<i32>());
-}
\ No newline at end of file
+    println!("Res 2: {}", alternates.iter().map(|reflection| {scoring.value(reflection)}).sum::<i32>());
+}
@@ -1,7 +1,9 @@
-use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
-use aoc_2023::read_lines_from_file;
+use std::hash::{Hash, Hasher};
+use aoc_2023::cycle;
+use aoc_2023::input::{flag_value, read_input};
 
+#[derive(Debug)]
 enum Direction {
     North,
     South,
@@ -9,13 +11,34 @@ enum Direction {
     West,
 }
 
+impl From<char> for Direction {
+    fn from(value: char) -> Self {
+        match value {
+            'N' => Direction::North,
+            'S' => Direction::South,
+            'E' => Direction::East,
+            'W' => Direction::West,
+            _ => panic!("Cant parse {value}"),
+        }
+    }
+}
+
 impl Direction {
-    fn vector(&self) -> (i32, i32) {
+    /// Number of lanes rocks roll along and the length of each, for a grid of `size`.
+    fn lanes(&self, size: (usize, usize)) -> (usize, usize) {
+        match self {
+            Direction::North | Direction::South => (size.0, size.1),
+            Direction::East | Direction::West => (size.1, size.0),
+        }
+    }
+
+    /// Grid position of `offset` within `lane`, counting from the edge rocks roll towards.
+    fn position(&self, size: (usize, usize), lane: usize, offset: usize) -> (usize, usize) {
         match self {
-            Direction::North => (0, -1),
-            Direction::South => (0, 1),
-            Direction::East => (1, 0),
-            Direction::West => (-1, 0),
+            Direction::North => (lane, offset),
+            Direction::South => (lane, size.1 - 1 - offset),
+            Direction::West => (offset, lane),
+            Direction::East => (size.0 - 1 - offset, lane),
         }
     }
 }
@@ -50,19 +73,58 @@ impl Debug for Tile {
     }
 }
 
-#[derive(Clone, PartialEq, Hash, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 struct Map {
     tiles: Vec<Vec<Tile>>,
+    /// Bit `y * width + x` is set for every round rock.
+    rounds: Vec<u64>,
+    /// Zobrist hash of the round rock positions, kept up to date as rocks move.
+    hash: u64,
 }
 
 impl From<&Vec<String>> for Map {
     fn from(value: &Vec<String>) -> Self {
-        Map {
-            tiles: value.iter().map(|line| {line.chars().map(Tile::from).collect()}).collect()
+        let tiles: Vec<Vec<Tile>> = value.iter().map(|line| {line.chars().map(Tile::from).collect()}).collect();
+        let (width, height) = tiles.size();
+        let mut res = Map {
+            rounds: vec![0; (width * height).div_ceil(64)],
+            hash: 0,
+            tiles,
+        };
+
+        for y in 0..height {
+            for x in 0..width {
+                if res.tiles[y][x] == Tile::Round {
+                    res.toggle(x, y);
+                }
+            }
         }
+
+        res
     }
 }
 
+/// Round rock positions of a platform, enough to tell apart tilts of the same platform.
+#[derive(Clone, PartialEq, Eq)]
+struct State {
+    hash: u64,
+    rounds: Vec<u64>,
+}
+
+impl Hash for State {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// Zobrist key of a cell, derived with splitmix64 so no table needs to be stored.
+fn zobrist(cell: usize) -> u64 {
+    let mut z = (cell as u64).wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 impl Debug for Map {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         for y in 0..self.tiles.size().1 {
@@ -101,149 +163,112 @@ impl At for Vec<Vec<Tile>> {
 }
 
 impl Map {
-    fn tilted(&self, dir: &Direction) -> Map {
-        let mut tiles = self.tiles.to_owned();
-
-        match dir {
-            Direction::North => {
-                for y in 0..self.tiles.size().1 {
-                    for x in 0..self.tiles.size().0 {
-                        if self.tiles.at(x as i32, y as i32) != Some(Tile::Round) {
-                            continue;
-                        }
-
-                        let pos = Map::final_position(&tiles, x, y, dir);
-                        if pos != (x, y) {
-                            tiles[y][x] = Tile::Empty;
-                            tiles[pos.1][pos.0] = Tile::Round;
-                        }
-                    }
-                }
-            }
-            Direction::South => {
-                for y in (0..self.tiles.size().1).rev() {
-                    for x in 0..self.tiles.size().0 {
-                        if self.tiles.at(x as i32, y as i32) != Some(Tile::Round) {
-                            continue;
-                        }
-
-                        let pos = Map::final_position(&tiles, x, y, dir);
-                        if pos != (x, y) {
-                            tiles[y][x] = Tile::Empty;
-                            tiles[pos.1][pos.0] = Tile::Round;
-                        }
-                    }
-                }
-            }
-            Direction::East => {
-                for x in (0..self.tiles.size().0).rev() {
-                    for y in 0..self.tiles.size().1 {
-                        if self.tiles.at(x as i32, y as i32) != Some(Tile::Round) {
-                            continue;
-                        }
+    /// Flips the round rock bit and hash of a cell after its tile changed.
+    fn toggle(&mut self, x: usize, y: usize) {
+        let cell = y * self.tiles.size().0 + x;
+        self.rounds[cell / 64] ^= 1 << (cell % 64);
+        self.hash ^= zobrist(cell);
+    }
 
-                        let pos = Map::final_position(&tiles, x, y, dir);
-                        if pos != (x, y) {
-                            tiles[y][x] = Tile::Empty;
-                            tiles[pos.1][pos.0] = Tile::Round;
-                        }
-                    }
-                }
-            }
-            Direction::West => {
-                for x in 0..self.tiles.size().0 {
-                    for y in 0..self.tiles.size().1 {
-                        if self.tiles.at(x as i32, y as i32) != Some(Tile::Round) {
-                            continue;
-                        }
+    fn state(&self) -> State {
+        State {
+            hash: self.hash,
+            rounds: self.rounds.clone(),
+        }
+    }
 
-                        let pos = Map::final_position(&tiles, x, y, dir);
-                        if pos != (x, y) {
-                            tiles[y][x] = Tile::Empty;
-                            tiles[pos.1][pos.0] = Tile::Round;
+    /// Tilts in place: each lane is split into segments by cube rocks, and the round rocks
+    /// counted in a segment are dropped against its leading end.
+    fn tilt(&mut self, dir: &Direction) {
+        let size = self.tiles.size();
+        let (lanes, length) = dir.lanes(size);
+
+        for lane in 0..lanes {
+            let mut start = 0;
+            let mut rounds = 0;
+
+            for offset in 0..=length {
+                let tile = if offset < length {
+                    let (x, y) = dir.position(size, lane, offset);
+                    self.tiles[y][x]
+                } else {
+                    Tile::Cube
+                };
+
+                match tile {
+                    Tile::Round => rounds += 1,
+                    Tile::Empty => {}
+                    Tile::Cube => {
+                        for fill in start..offset {
+                            let (x, y) = dir.position(size, lane, fill);
+                            let tile = if fill - start < rounds { Tile::Round } else { Tile::Empty };
+                            if self.tiles[y][x] != tile {
+                                self.tiles[y][x] = tile;
+                                self.toggle(x, y);
+                            }
                         }
+                        start = offset + 1;
+                        rounds = 0;
                     }
                 }
             }
         }
-
-        Map{tiles}
     }
 
-    fn circle(&self) -> Map {
-        self.tilted(&Direction::North).tilted(&Direction::West).tilted(&Direction::South).tilted(&Direction::East)
+    fn tilted(&self, dir: &Direction) -> Map {
+        let mut res = self.clone();
+        res.tilt(dir);
+        res
     }
 
-    fn score(&self) -> i32 {
-        let mut res = 0;
-        for y in 0..self.tiles.size().1 {
-            for x in 0..self.tiles.size().0 {
-                match self.tiles.at(x as i32,y as i32) {
-                    None => {}
-                    Some(tile) => {
-                        match tile {
-                            Tile::Round => res += self.tiles.size().1 - y,
-                            _ => {}
-                        }
-                    }
-                }
-            }
+    fn spin(&mut self, sequence: &[Direction]) {
+        for dir in sequence {
+            self.tilt(dir);
         }
-
-        res as i32
     }
 
-    fn final_position(tiles: &Vec<Vec<Tile>>, x: usize, y: usize, dir: &Direction) -> (usize, usize) {
+    fn circle(&self, sequence: &[Direction]) -> Map {
+        let mut res = self.clone();
+        res.spin(sequence);
+        res
+    }
 
-        let mut current = (x as i32, y as i32);
-        loop {
-            let next = (current.0 + dir.vector().0, current.1 + dir.vector().1);
-            match tiles.at(next.0, next.1) {
-                None => break,
-                Some(tile) => {
-                    match tile {
-                        Tile::Empty => current = next,
-                        _ => break,
-                    }
+    /// Load on the support beams at the `dir` edge: each round rock weighs its distance
+    /// from the opposite edge.
+    fn score(&self, dir: &Direction) -> i32 {
+        let (width, height) = self.tiles.size();
+        let mut res = 0;
+        for y in 0..height {
+            for x in 0..width {
+                if let Some(Tile::Round) = self.tiles.at(x as i32, y as i32) {
+                    res += match dir {
+                        Direction::North => height - y,
+                        Direction::South => y + 1,
+                        Direction::West => width - x,
+                        Direction::East => x + 1,
+                    };
                 }
             }
         }
 
-        (current.0 as usize, current.1 as usize)
+        res as i32
     }
 }
 
 fn main() {
-    let lines = read_lines_from_file("day14.input");
+    let lines = read_input(14);
     let map = Map::from(&lines);
 
     let tilted = map.tilted(&Direction::North);
-    println!("Score: {}", tilted.score());
-
-    let mut seen: HashMap<Map, i32> = HashMap::new();
-    let mut indexed: HashMap<i32, Map> = HashMap::new();
-    let mut circle = map.circle();
-    let mut count = 1;
-    let start;
-    seen.insert(circle.clone(), count);
-    indexed.insert(count, circle.clone());
-
-    loop {
-        let next = circle.circle();
-        count += 1;
-
-        if seen.contains_key(&next) {
-            println!("Cycle: {count}");
-            start = seen[&next];
-            println!("First seen: {}", seen[&next]);
-            break;
-        }
+    println!("Score: {}", tilted.score(&Direction::North));
 
-        circle = next;
-        seen.insert(circle.clone(), count);
-        indexed.insert(count, circle.clone());
-    }
+    let sequence = flag_value("--sequence").unwrap_or(String::from("NWSE")).chars().map(Direction::from).collect::<Vec<_>>();
+    let target = flag_value("--cycles").map(|value| {value.parse::<u64>().unwrap_or_else(|_| {panic!("Cant parse {value}")})}).unwrap_or(1_000_000_000);
+    let load = flag_value("--load").and_then(|value| {value.chars().next()}).map(Direction::from).unwrap_or(Direction::North);
+
+    let cycle = cycle::hashed_by(&map, |map| {map.circle(&sequence)}, Map::state);
+    println!("Sequence: {:?} Tail: {} Period: {}", sequence, cycle.tail, cycle.period);
 
-    let target = (1_000_000_000 - start) % (count - start) + start;
-    println!("Tar: {target} Val: {}",indexed[&target].score());
-}
\ No newline at end of file
+    let res = cycle::advance(&map, |map| {map.circle(&sequence)}, &cycle, target);
+    println!("Tar: {} Val: {}", cycle.equivalent(target), res.score(&load));
+}
@@ -1,6 +1,9 @@
 use std::array::from_fn;
 use std::collections::HashMap;
-use aoc_2023::read_lines_from_file;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use aoc_2023::input::{flag_value, input_path};
 
 fn hash(input: &str) -> i32 {
     let mut res = 0;
@@ -11,19 +14,130 @@ fn hash(input: &str) -> i32 {
     res
 }
 
+#[derive(Clone, Debug)]
+struct Lens {
+    label: String,
+    length: i32,
+}
+
+/// Lenses in insertion order. Removed lenses leave a tombstone behind so no other lens
+/// has to move; tombstones are compacted away once they outnumber the lenses.
 #[derive(Clone, Debug)]
 struct Box {
-    lenses: Vec<i32>,
-    index: HashMap<String, i32>,
+    slots: Vec<Option<Lens>>,
+    index: HashMap<String, usize>,
 }
 
 impl Box {
     fn new() -> Box {
         Box {
-            lenses: vec![],
+            slots: vec![],
             index: HashMap::new(),
         }
     }
+
+    fn insert(&mut self, label: &str, length: i32) {
+        match self.index.get(label) {
+            Some(slot) => self.slots[*slot].as_mut().unwrap().length = length,
+            None => {
+                self.index.insert(label.to_owned(), self.slots.len());
+                self.slots.push(Some(Lens { label: label.to_owned(), length }));
+            }
+        }
+    }
+
+    fn remove(&mut self, label: &str) -> Option<i32> {
+        let slot = self.index.remove(label)?;
+        let lens = self.slots[slot].take().unwrap();
+
+        if self.slots.len() > 2 * self.index.len() {
+            self.compact();
+        }
+
+        Some(lens.length)
+    }
+
+    fn compact(&mut self) {
+        self.slots.retain(Option::is_some);
+        for (slot, lens) in self.slots.iter().enumerate() {
+            *self.index.get_mut(&lens.as_ref().unwrap().label).unwrap() = slot;
+        }
+    }
+
+    fn get(&self, label: &str) -> Option<i32> {
+        self.index.get(label).map(|slot| {self.slots[*slot].as_ref().unwrap().length})
+    }
+
+    fn lenses(&self) -> impl Iterator<Item = &Lens> {
+        self.slots.iter().flatten()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+impl Display for Box {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.lenses().map(|lens| {format!("[{} {}]", lens.label, lens.length)}).collect::<Vec<_>>().join(" "))
+    }
+}
+
+/// The HASHMAP: 256 boxes of lenses, each lens stored in the box its label hashes to.
+struct LensHashMap {
+    boxes: [Box; 256],
+}
+
+impl LensHashMap {
+    fn new() -> LensHashMap {
+        LensHashMap {
+            boxes: from_fn(|_| {Box::new()}),
+        }
+    }
+
+    fn insert(&mut self, label: &str, length: i32) {
+        self.boxes[hash(label) as usize].insert(label, length)
+    }
+
+    fn remove(&mut self, label: &str) -> Option<i32> {
+        self.boxes[hash(label) as usize].remove(label)
+    }
+
+    fn get(&self, label: &str) -> Option<i32> {
+        self.boxes[hash(label) as usize].get(label)
+    }
+
+    fn apply(&mut self, step: &Step) {
+        match &step.op {
+            Operation::Remove => {
+                self.remove(&step.label);
+            }
+            Operation::Add => self.insert(&step.label, step.length.unwrap()),
+        }
+    }
+
+    /// Every lens as `(box, slot, lens)`, in box then slot order.
+    fn iter(&self) -> impl Iterator<Item = (usize, usize, &Lens)> {
+        self.boxes.iter().enumerate().flat_map(|(box_index, b)| {
+            b.lenses().enumerate().map(move |(slot, lens)| {(box_index, slot, lens)})
+        })
+    }
+
+    fn focusing_power(&self) -> u64 {
+        self.iter().map(|(box_index, slot, lens)| {
+            lens.length as u64 * (slot as u64 + 1) * (box_index as u64 + 1)
+        }).sum::<u64>()
+    }
+}
+
+impl Display for LensHashMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (box_index, b) in self.boxes.iter().enumerate().filter(|(_, b)| {!b.is_empty()}) {
+            writeln!(f, "Box {}: {}", box_index, b)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -49,6 +163,15 @@ struct Step {
     length: Option<i32>,
 }
 
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.op {
+            Operation::Remove => write!(f, "{}-", self.label),
+            Operation::Add => write!(f, "{}={}", self.label, self.length.unwrap()),
+        }
+    }
+}
+
 impl From<&str> for Step {
     fn from(value: &str) -> Self {
         let idx_op = value.find(|x| { x == '=' || x == '-' }).unwrap();
@@ -67,40 +190,77 @@ impl From<&str> for Step {
     }
 }
 
-fn main() {
-    let line = read_lines_from_file("day15.input")[0].to_owned();
-    let steps = line.split(',').map(Step::from).collect::<Vec<_>>();
-    let mut boxes: [Box; 256] = from_fn(|_| {Box::new()});
+/// Comma-separated steps read incrementally from any source, newlines ignored.
+struct Tokens<R: BufRead> {
+    source: R,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> Tokens<BufReader<R>> {
+    fn new(source: R) -> Self {
+        Tokens {
+            source: BufReader::new(source),
+            buffer: vec![],
+        }
+    }
+}
 
-    for step in steps {
-        let target = hash(&step.label);
+impl<R: BufRead> Iterator for Tokens<R> {
+    type Item = String;
 
-        match &step.op {
-            Operation::Remove => {
-                if boxes[target as usize].index.contains_key(&step.label) {
-                    let index = boxes[target as usize].index[&step.label];
-                    boxes[target as usize].lenses.remove(index as usize);
-                    boxes[target as usize].index.remove(&step.label);
-                    boxes[target as usize].index = boxes[target as usize].index.iter().map(|(label,idx)| { if idx > &index {(label.to_owned(), idx - 1)} else {(label.to_owned(), *idx)} }).collect();
-                }
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buffer.clear();
+            let read = self.source.read_until(b',', &mut self.buffer).expect("Cant read initialization sequence");
+            if read == 0 {
+                return None;
             }
-            Operation::Add => {
-                if boxes[target as usize].index.contains_key(&step.label) {
-                    let index = boxes[target as usize].index[&step.label];
-                    boxes[target as usize].lenses[index as usize] = step.length.unwrap();
-                } else {
-                    boxes[target as usize].lenses.push(step.length.unwrap());
-                    boxes[target as usize].index.insert(step.label,boxes[target as usize].lenses.len() as i32 - 1);
-                }
+
+            self.buffer.retain(|byte| {*byte != b',' && *byte != b'\n' && *byte != b'\r'});
+            if !self.buffer.is_empty() {
+                return Some(String::from_utf8_lossy(&self.buffer).into_owned());
             }
         }
     }
+}
+
+fn main() {
+    let path = input_path(15);
+    let file = File::open(&path).unwrap_or_else(|_| {panic!("File {} not found!", path.display())});
+
+    let trace = std::env::args().any(|arg| {arg == "--trace"});
+    let until = flag_value("--until").map(|value| {value.parse::<usize>().unwrap_or_else(|_| {panic!("Cant parse {value}")})});
+    let label = flag_value("--label");
+
+    let mut boxes = LensHashMap::new();
+    let mut hash_sum: u64 = 0;
+    let mut stopped = false;
+
+    for (idx, token) in Tokens::new(file).enumerate() {
+        hash_sum += hash(&token) as u64;
+        if stopped {
+            continue;
+        }
+
+        let step = Step::from(token.as_str());
+        boxes.apply(&step);
+
+        if trace {
+            println!("After \"{}\":\n{}", step, boxes);
+        }
+
+        if until == Some(idx + 1) || label.as_deref() == Some(step.label.as_str()) {
+            println!("Stopped after step {}: \"{}\"", idx + 1, step);
+            stopped = true;
+        }
+    }
 
-    let res = boxes.iter().enumerate().map(|(box_index, b)| {
-        b.lenses.iter().enumerate().map(|(lens_index, length)| {
-            length * (lens_index as i32 + 1) * (box_index as i32 + 1)
-        }).sum::<i32>()
-    }).sum::<i32>();
+    println!("Hash sum: {}", hash_sum);
+    print!("{}", boxes);
 
-    println!("Res: {}", res);
-}
\ No newline at end of file
+    if let Some(label) = flag_value("--get") {
+        println!("{}: {:?}", label, boxes.get(&label));
+    }
+
+    println!("Res: {}", boxes.focusing_power());
+}
@@ -1,4 +1,4 @@
-use aoc_2023::read_lines_from_file;
+use aoc_2023::input::read_input;
 
 #[derive(Debug)]
 struct Set {
@@ -61,7 +61,7 @@ impl Game {
     }
 }
 fn main() {
-    let lines = read_lines_from_file("day2.input");
+    let lines = read_input(2);
     let games = lines.iter().map(|line| {Game::from(&line)}).collect::<Vec<_>>();
     let result: i32 = games.iter().filter_map(|g| {if g.possible(12,13,14) {Some(g.id)} else {None}}).sum();
     let power: i32 = games.iter().map(|game| {game.power()}).sum();
@@ -1,5 +1,5 @@
 use std::cmp::{max, min};
-use aoc_2023::read_lines_from_file;
+use aoc_2023::input::read_input;
 
 trait At<T> {
     fn at(&self, row: i32, col: i32) -> T;
@@ -173,7 +173,7 @@ impl Schema {
 }
 
 fn main() {
-    let schema = Schema::from(read_lines_from_file("day3.input"));
+    let schema = Schema::from(read_input(3));
     println!("{:?}", schema.numbers.iter().map(|x| {x.number}).sum::<i32>());
     println!("{:?}", schema.gears.iter().map(|gear| {gear.ratio()}).sum::<i32>());
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use aoc_2023::read_lines_from_file;
+use aoc_2023::input::read_input;
 
 #[derive(Debug)]
 struct Card {
@@ -34,7 +34,7 @@ impl Card {
 }
 
 fn main() {
-    let games = read_lines_from_file("day4.input").iter().map(|line| {Card::from(line)}).collect::<Vec<_>>();
+    let games = read_input(4).iter().map(|line| {Card::from(line)}).collect::<Vec<_>>();
     println!("Part 1: {}", games.iter().map(|card| {card.value()}).sum::<i32>());
 
     let mut counts: Vec<i32> = games.iter().map(|_| {1}).collect();
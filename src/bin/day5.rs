@@ -1,6 +1,6 @@
 use std::cmp::{max, min};
 use std::ops::Range;
-use aoc_2023::read_lines_from_file;
+use aoc_2023::input::read_input;
 
 #[derive(Default, Debug)]
 struct Map {
@@ -85,7 +85,7 @@ impl Rule {
 }
 
 fn main() {
-    let lines = read_lines_from_file("day5.input");
+    let lines = read_input(5);
     let seeds: Vec<i64> = lines[0][lines[0].find(':').unwrap() + 1..].split(' ').filter_map(|num| {
         num.trim().parse::<i64>().ok()}).collect();
     let maps = lines[1..].split(|line| { line.is_empty() }).filter(|x| { !x.is_empty() }).map(|maplines| { Map::new(maplines) }).collect::<Vec<_>>();
@@ -1,4 +1,4 @@
-use aoc_2023::read_lines_from_file;
+use aoc_2023::input::read_input;
 
 
 fn distance_for_time(charge: i64, time: i64) -> i64 {
@@ -9,7 +9,7 @@ fn distance_for_time(charge: i64, time: i64) -> i64 {
     }
 }
 fn main() {
-    let lines = read_lines_from_file("day6.input");
+    let lines = read_input(6);
     let times = lines[0][lines[0].find(':').unwrap()+1..].split(' ').filter_map(|x| {x.trim().parse::<i64>().ok()}).collect::<Vec<_>>();
     let distances = lines[1][lines[1].find(':').unwrap()+1..].split(' ').filter_map(|x| {x.trim().parse::<i64>().ok()}).collect::<Vec<_>>();
 
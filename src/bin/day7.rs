@@ -1,6 +1,6 @@
 use std::cmp::Ordering;
 use std::collections::HashMap;
-use aoc_2023::read_lines_from_file;
+use aoc_2023::input::read_input;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum Card {
@@ -175,7 +175,7 @@ impl Hand {
 }
 
 fn main() {
-    let lines = read_lines_from_file("day7.input");
+    let lines = read_input(7);
     let mut hands = lines.iter().map(|x| {Hand::new(x)}).collect::<Vec<_>>();
 
     hands.sort();
@@ -1,18 +1,20 @@
 use std::collections::HashMap;
-use aoc_2023::read_lines_from_file;
+use std::fmt::{Display, Formatter};
+use aoc_2023::math::{crt, lcm, modulo, MathError};
+use aoc_2023::input::{flag_value, read_input};
 
 
 #[derive(Debug)]
 enum Instruction {
-    LEFT,
-    RIGHT,
+    Left,
+    Right,
 }
 
 impl Instruction {
     fn new (input: char) -> Instruction {
         match input {
-            'L' => Instruction::LEFT,
-            'R' => Instruction::RIGHT,
+            'L' => Instruction::Left,
+            'R' => Instruction::Right,
             _ => panic!("Cant parse {}", input)
         }
     }
@@ -26,7 +28,7 @@ struct Node {
 }
 
 impl Node {
-    fn new(input: &String) -> Node {
+    fn new(input: &str) -> Node {
         Node{
         id: input[0..input.find('=').unwrap()].trim().to_string(),
         left: input[input.find('(').unwrap()+1..input.find(',').unwrap()].trim().to_string(),
@@ -35,75 +37,370 @@ impl Node {
     }
 }
 
-fn gcd(a: i64, b: i64) -> i64 {
-    match b {
-        0 => a,
-        _ => gcd(b, a % b)
+enum Export {
+    Dot,
+    Adjacency,
+}
+
+impl From<&str> for Export {
+    fn from(value: &str) -> Self {
+        match value {
+            "dot" => Export::Dot,
+            "adjacency" => Export::Adjacency,
+            _ => panic!("Cant parse {value}, expected dot or adjacency"),
+        }
+    }
+}
+
+impl Export {
+    fn render(&self, nodes: &[Node]) -> String {
+        match self {
+            Export::Dot => {
+                let mut res = String::from("digraph network {\n");
+                for node in nodes {
+                    if node.id.ends_with('A') {
+                        res.push_str(&format!("    \"{}\" [style=filled, fillcolor=palegreen];\n", node.id));
+                    } else if node.id.ends_with('Z') {
+                        res.push_str(&format!("    \"{}\" [style=filled, fillcolor=salmon];\n", node.id));
+                    }
+                }
+                for node in nodes {
+                    res.push_str(&format!("    \"{}\" -> \"{}\" [label=\"L\"];\n", node.id, node.left));
+                    res.push_str(&format!("    \"{}\" -> \"{}\" [label=\"R\"];\n", node.id, node.right));
+                }
+                res.push_str("}\n");
+                res
+            }
+            Export::Adjacency => {
+                nodes.iter().map(|node| {format!("{} {} {}\n", node.id, node.left, node.right)}).collect()
+            }
+        }
     }
 }
 
-fn lcm(a: i64, b: i64) -> i64 {
-    a / gcd(a,b) * b
+#[derive(Debug)]
+enum NetworkError {
+    NoInstructions,
+    DuplicateNode(String),
+    MissingNode(String),
+    DanglingEdge { from: String, to: String },
+    Unreachable { start: String, steps: usize },
 }
-fn main() {
-    let lines = read_lines_from_file("day8.input");
-    let instructions = lines[0].chars().map(Instruction::new).collect::<Vec<_>>();
-    for instruction in &instructions {
-        println!("{:?}", instruction);
+
+impl Display for NetworkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkError::NoInstructions => write!(f, "no instructions given"),
+            NetworkError::DuplicateNode(id) => write!(f, "node {id} is defined more than once"),
+            NetworkError::MissingNode(id) => write!(f, "node {id} does not exist"),
+            NetworkError::DanglingEdge { from, to } => write!(f, "node {from} points to undefined node {to}"),
+            NetworkError::Unreachable { start, steps } => write!(f, "no target reachable from {start}, state repeats after {steps} steps"),
+        }
     }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// Nodes interned into a compact index table, edges stored as indices.
+struct Network {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    left: Vec<usize>,
+    right: Vec<usize>,
+}
 
-    let nodes = lines[2..].iter().map(Node::new).map(|node: Node| {(node.id.to_string(), node)}).collect::<HashMap<_,_>>();
-    for node in &nodes {
-        println!("{:?}", node);
+impl Network {
+    fn new(nodes: &[Node]) -> Result<Network, NetworkError> {
+        let mut ids: HashMap<String, usize> = HashMap::new();
+        for (idx, node) in nodes.iter().enumerate() {
+            if ids.insert(node.id.to_owned(), idx).is_some() {
+                return Err(NetworkError::DuplicateNode(node.id.to_owned()));
+            }
+        }
+
+        let edge = |from: &Node, to: &String| {
+            ids.get(to).copied().ok_or_else(|| {NetworkError::DanglingEdge { from: from.id.to_owned(), to: to.to_owned() }})
+        };
+
+        Ok(Network {
+            names: nodes.iter().map(|node| {node.id.to_owned()}).collect(),
+            left: nodes.iter().map(|node| {edge(node, &node.left)}).collect::<Result<_, _>>()?,
+            right: nodes.iter().map(|node| {edge(node, &node.right)}).collect::<Result<_, _>>()?,
+            ids,
+        })
     }
 
-    let mut steps = 0;
-    let mut current = String::from("AAA");
+    fn id(&self, name: &str) -> Result<usize, NetworkError> {
+        self.ids.get(name).copied().ok_or_else(|| {NetworkError::MissingNode(name.to_owned())})
+    }
 
-    loop {
-        let instruction = &instructions[steps % instructions.len()];
-        match instruction {
-            Instruction::LEFT => current = nodes[&current].left.to_string(),
-            Instruction::RIGHT => current = nodes[&current].right.to_string(),
+    /// Steps from `start` until `target`, failing once a (node, instruction index) state repeats.
+    fn walk(&self, start: usize, target: usize, instructions: &[Instruction]) -> Result<usize, NetworkError> {
+        if instructions.is_empty() {
+            return Err(NetworkError::NoInstructions);
         }
-        steps += 1;
 
-        if current == "ZZZ" {
-            break;
+        let mut seen: Vec<bool> = vec![false; self.names.len() * instructions.len()];
+        let mut current = start;
+        let mut steps = 0;
+
+        loop {
+            let state = current * instructions.len() + steps % instructions.len();
+            if seen[state] {
+                return Err(NetworkError::Unreachable { start: self.names[start].to_owned(), steps });
+            }
+            seen[state] = true;
+
+            current = self.next(current, &instructions[steps % instructions.len()]);
+            steps += 1;
+
+            if current == target {
+                return Ok(steps);
+            }
+        }
+    }
+
+    fn next(&self, node: usize, instruction: &Instruction) -> usize {
+        match instruction {
+            Instruction::Left => self.left[node],
+            Instruction::Right => self.right[node],
         }
     }
 
-    println!("{}", steps);
+    fn is_end(&self, node: usize) -> bool {
+        self.names[node].ends_with('Z')
+    }
 
-    let currents: Vec<String> = nodes.keys().filter(|key| {key.ends_with('A')}).map(|x| {x.to_owned()}).collect::<Vec<_>>();
-    println!("Starters: {:?}", currents);
-    let mut cycles: Vec<i64> = vec![];
+    fn cycle(&self, start: usize, instructions: &[Instruction]) -> Result<Cycle, NetworkError> {
+        if instructions.is_empty() {
+            return Err(NetworkError::NoInstructions);
+        }
 
-    for c in currents {
+        let mut seen: Vec<Option<usize>> = vec![None; self.names.len() * instructions.len()];
+        let mut hits: Vec<usize> = vec![];
+        let mut node = start;
         let mut steps = 0;
-        let mut current = c;
+
         loop {
-            let instruction = &instructions[steps % instructions.len()];
-            match instruction {
-                Instruction::LEFT => current = nodes[&current].left.to_string(),
-                Instruction::RIGHT => current = nodes[&current].right.to_string(),
+            let state = node * instructions.len() + steps % instructions.len();
+            if let Some(first) = seen[state] {
+                if hits.is_empty() {
+                    return Err(NetworkError::Unreachable { start: self.names[start].to_owned(), steps });
+                }
+
+                return Ok(Cycle {
+                    tail: first,
+                    length: steps - first,
+                    hits,
+                });
+            }
+            seen[state] = Some(steps);
+
+            if self.is_end(node) {
+                hits.push(steps);
             }
+
+            node = self.next(node, &instructions[steps % instructions.len()]);
             steps += 1;
+        }
+    }
+}
 
-            if current.ends_with('Z') {
-                break;
+/// Walk of a single start over (node, instruction index) states: `tail` steps before the
+/// first repeated state, then a cycle of `length` steps. `hits` are the steps at which a
+/// `..Z` node is reached, all smaller than `tail + length`.
+#[derive(Debug)]
+struct Cycle {
+    tail: usize,
+    length: usize,
+    hits: Vec<usize>,
+}
+
+impl Cycle {
+    fn hit_at(&self, step: usize) -> bool {
+        if step < self.tail {
+            self.hits.contains(&step)
+        } else {
+            self.hits.contains(&(self.tail + (step - self.tail) % self.length))
+        }
+    }
+
+    fn periodic_hits(&self) -> Vec<usize> {
+        self.hits.iter().filter(|hit| {**hit >= self.tail}).copied().collect()
+    }
+
+    /// The puzzle's implicit assumption: the only target is hit exactly once per cycle,
+    /// at a step equal to the cycle length, so all ghosts line up at multiples of it. The
+    /// hit must lie inside the cycle, otherwise it is never repeated.
+    fn simple(&self) -> bool {
+        self.hits == [self.length] && self.tail <= self.length
+    }
+}
+
+fn synchronise(cycles: &[Cycle]) -> Result<Option<i64>, MathError> {
+    if cycles.iter().all(Cycle::simple) {
+        return cycles.iter().try_fold(1, |acc, cycle| {lcm(acc, cycle.length as i64)}).map(Some);
+    }
+
+    let mut candidates: Vec<i64> = vec![];
+
+    for cycle in cycles {
+        for hit in cycle.hits.iter().filter(|hit| {**hit >= 1 && **hit < cycle.tail}) {
+            if cycles.iter().all(|other| {other.hit_at(*hit)}) {
+                candidates.push(*hit as i64);
             }
         }
+    }
+
+    let mut congruences: Vec<(i64, i64)> = vec![(0, 1)];
+    for cycle in cycles {
+        let mut next: Vec<(i64, i64)> = vec![];
+        for congruence in &congruences {
+            for hit in cycle.periodic_hits() {
+                match crt(&[*congruence, (hit as i64, cycle.length as i64)]) {
+                    Ok(combined) => next.push(combined),
+                    Err(MathError::Inconsistent) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        congruences = next;
+    }
 
-        cycles.push(steps as i64)
+    let lower = cycles.iter().map(|cycle| {cycle.tail}).max().unwrap_or(0).max(1) as i64;
+    for (residue, modulus) in congruences {
+        let first = lower + modulo(residue - lower, modulus)?;
+        candidates.push(first);
     }
 
-    let mut acc = 1;
+    Ok(candidates.into_iter().min())
+}
+
+fn main() {
+    let lines = read_input(8);
+    let instructions = lines[0].chars().map(Instruction::new).collect::<Vec<_>>();
+    let nodes = lines[2..].iter().map(|line| {Node::new(line)}).collect::<Vec<_>>();
 
-    for cycle in &cycles {
-        acc = lcm(acc, *cycle)
+    if let Some(format) = flag_value("--export") {
+        print!("{}", Export::from(format.as_str()).render(&nodes));
+        return;
     }
 
-    println!("{}", acc);
+    let network = match Network::new(&nodes) {
+        Ok(network) => network,
+        Err(err) => {
+            eprintln!("Invalid network: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    match network.id("AAA").and_then(|start| {network.walk(start, network.id("ZZZ")?, &instructions)}) {
+        Ok(steps) => println!("{}", steps),
+        Err(err) => println!("Cant walk AAA to ZZZ: {err}"),
+    }
 
-}
\ No newline at end of file
+    let starters = (0..network.names.len()).filter(|node| {network.names[*node].ends_with('A')}).collect::<Vec<_>>();
+    println!("Starters: {:?}", starters.iter().map(|node| {&network.names[*node]}).collect::<Vec<_>>());
+
+    let cycles = match starters.iter().map(|start| {network.cycle(*start, &instructions)}).collect::<Result<Vec<_>, _>>() {
+        Ok(cycles) => cycles,
+        Err(err) => {
+            println!("Cant synchronise: {err}");
+            return;
+        }
+    };
+    for (start, cycle) in starters.iter().zip(&cycles) {
+        println!("{}: tail {} length {} hits {:?}", network.names[*start], cycle.tail, cycle.length, cycle.hits);
+    }
+
+    match synchronise(&cycles) {
+        Ok(Some(steps)) => println!("{}", steps),
+        Ok(None) => println!("Never synchronised"),
+        Err(err) => println!("Cant synchronise: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc_2023::input::{resolve, Source};
+    use aoc_2023::read_lines_from_file;
+
+    fn synchronised(lines: &[String]) -> Result<Option<i64>, MathError> {
+        let instructions = lines[0].chars().map(Instruction::new).collect::<Vec<_>>();
+        let nodes = lines[2..].iter().map(|line| {Node::new(line)}).collect::<Vec<_>>();
+        let network = Network::new(&nodes).unwrap();
+        let cycles = (0..network.names.len()).filter(|node| {network.names[*node].ends_with('A')})
+            .map(|start| {network.cycle(start, &instructions).unwrap()}).collect::<Vec<_>>();
+        synchronise(&cycles)
+    }
+
+    fn lines(input: &str) -> Vec<String> {
+        input.lines().map(|line| {line.trim().to_string()}).collect()
+    }
+
+    /// A ghost whose only target lies in its tail, before a 3-cycle of YY nodes.
+    const TAIL_HIT: &str = "L
+
+        AAA = (XX1, XX1)
+        XX1 = (XX2, XX2)
+        XX2 = (QQZ, QQZ)
+        QQZ = (YY1, YY1)
+        YY1 = (YY2, YY2)
+        YY2 = (YY3, YY3)
+        YY3 = (YY1, YY1)";
+
+    #[test]
+    fn tail_hit_is_not_periodic() {
+        let input = format!("{TAIL_HIT}
+            BBA = (BB1, BB1)
+            BB1 = (BB2, BB2)
+            BB2 = (BB3, BB3)
+            BB3 = (BB4, BB4)
+            BB4 = (BB5, BB5)
+            BB5 = (BBZ, BBZ)
+            BBZ = (BB1, BB1)");
+        assert_eq!(synchronised(&lines(&input)), Ok(None));
+    }
+
+    #[test]
+    fn tail_hit_shared() {
+        let input = format!("{TAIL_HIT}
+            BBA = (BB1, BB1)
+            BB1 = (BB2, BB2)
+            BB2 = (BBZ, BBZ)
+            BBZ = (BB1, BB1)");
+        assert_eq!(synchronised(&lines(&input)), Ok(Some(3)));
+    }
+
+    #[test]
+    fn periodic_hits_by_crt() {
+        // ghosts hit every 4 steps from step 3 and every 6 steps from step 5
+        let input = "L
+
+            AAA = (A1, A1)
+            A1 = (A2, A2)
+            A2 = (AAZ, AAZ)
+            AAZ = (A4, A4)
+            A4 = (A5, A5)
+            A5 = (A6, A6)
+            A6 = (AAZ, AAZ)
+            BBA = (B1, B1)
+            B1 = (B2, B2)
+            B2 = (B3, B3)
+            B3 = (B4, B4)
+            B4 = (BBZ, BBZ)
+            BBZ = (B6, B6)
+            B6 = (B7, B7)
+            B7 = (B8, B8)
+            B8 = (B9, B9)
+            B9 = (B10, B10)
+            B10 = (BBZ, BBZ)";
+        assert_eq!(synchronised(&lines(input)), Ok(Some(11)));
+    }
+
+    #[test]
+    fn examples() {
+        assert_eq!(synchronised(&read_lines_from_file(resolve(8, &Source::Example(3)))), Ok(Some(6)));
+    }
+}
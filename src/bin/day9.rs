@@ -1,68 +1,159 @@
-use aoc_2023::read_lines_from_file;
+use std::fmt::{Display, Formatter};
+use aoc_2023::math::{MathError, Rational};
+use aoc_2023::input::{flag_value, read_input};
+
+#[derive(Debug)]
+enum SeriesError {
+    Empty,
+    Undetermined,
+    Overflow,
+}
+
+impl Display for SeriesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SeriesError::Empty => write!(f, "series has no values"),
+            SeriesError::Undetermined => write!(f, "differences never reach an all-zero row"),
+            SeriesError::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+impl std::error::Error for SeriesError {}
+
+impl From<MathError> for SeriesError {
+    fn from(_: MathError) -> Self {
+        SeriesError::Overflow
+    }
+}
 
 #[derive(Debug)]
 struct Series {
-    values: Vec<i32>,
+    values: Vec<i64>,
 }
 
 impl Series {
-    fn new(line: &String) -> Series {
+    fn new(line: &str) -> Series {
         Series {
-            values: line.split(' ').map(|s| {s.trim()}).filter(|s| {!s.is_empty()}).map(|s| {s.parse::<i32>().unwrap()}).collect()
+            values: line.split(' ').map(|s| {s.trim()}).filter(|s| {!s.is_empty()}).map(|s| {s.parse::<i64>().unwrap()}).collect()
         }
     }
 
-    fn next(&self) -> (i32, i32) {
+    /// Leading entries of each difference row, i.e. the Newton forward differences
+    /// `Δ^j f(0)` of the fitted polynomial, up to (excluding) the first all-zero row.
+    fn forward_differences(&self) -> Result<Vec<i128>, SeriesError> {
+        if self.values.is_empty() {
+            return Err(SeriesError::Empty);
+        }
 
-        let mut stack: Vec<Vec<i32>> = vec![self.values.clone()];
-        let mut current = &stack[0];
+        let mut res: Vec<i128> = vec![];
+        let mut current = self.values.iter().map(|x| {*x as i128}).collect::<Vec<_>>();
 
-        loop {
-            let mut res: Vec<i32> = vec![];
+        while current.iter().any(|x| {*x != 0}) {
+            if current.len() == 1 {
+                return Err(SeriesError::Undetermined);
+            }
 
-            for pair in current.windows(2) {
-                if pair.len() == 1 {
-                    break;
-                }
+            res.push(current[0]);
+            current = current.windows(2).map(|pair| {pair[1].checked_sub(pair[0])}).collect::<Option<Vec<_>>>().ok_or(SeriesError::Overflow)?;
+        }
 
-                res.push(pair[1] - pair[0]);
-            }
+        Ok(res)
+    }
 
-            if res.iter().all(|x| {*x == 0}) {
-                break;
-            }
+    fn degree(&self) -> Result<usize, SeriesError> {
+        Ok(self.forward_differences()?.len().saturating_sub(1))
+    }
+
+    /// Value of the fitted polynomial at position `x`, where 0 is the first value.
+    fn at(&self, x: i64) -> Result<i64, SeriesError> {
+        let mut res: i128 = 0;
+        let mut binomial: i128 = 1;
 
-            stack.push(res);
-            current = stack.last().unwrap();
+        for (j, difference) in self.forward_differences()?.iter().enumerate() {
+            if j > 0 {
+                binomial = binomial.checked_mul(x as i128 - j as i128 + 1).ok_or(SeriesError::Overflow)? / j as i128;
+            }
+            res = difference.checked_mul(binomial).and_then(|term| {res.checked_add(term)}).ok_or(SeriesError::Overflow)?;
         }
 
-        let mut right = 0;
+        i64::try_from(res).map_err(|_| {SeriesError::Overflow})
+    }
 
-        for value in stack.iter().map(|v| {v.last().unwrap()}).rev() {
-            right = value + right;
-        }
+    /// The `k`-th value after the last one.
+    fn forward(&self, k: i64) -> Result<i64, SeriesError> {
+        let last = i64::try_from(self.values.len()).map_err(|_| {SeriesError::Overflow})? - 1;
+        self.at(last.checked_add(k).ok_or(SeriesError::Overflow)?)
+    }
+
+    /// The `k`-th value before the first one.
+    fn backward(&self, k: i64) -> Result<i64, SeriesError> {
+        self.at(k.checked_neg().ok_or(SeriesError::Overflow)?)
+    }
 
-        let mut left = 0;
+    fn next(&self) -> Result<(i64, i64), SeriesError> {
+        self.extrapolate(1)
+    }
 
-        for value in stack.iter().map(|v| {v.first().unwrap()}).rev() {
-            left = value - left;
+    /// Values `k` steps before the first and after the last one.
+    fn extrapolate(&self, k: i64) -> Result<(i64, i64), SeriesError> {
+        Ok((self.backward(k)?, self.forward(k)?))
+    }
+
+    /// Coefficients of the fitted polynomial in `x`, lowest power first.
+    fn coefficients(&self) -> Result<Vec<Rational>, SeriesError> {
+        let differences = self.forward_differences()?;
+        let mut res = vec![Rational::integer(0); differences.len().max(1)];
+        // x (x - 1) ... (x - j + 1), lowest power first
+        let mut falling: Vec<i128> = vec![1];
+        let mut factorial: i128 = 1;
+
+        for (j, difference) in differences.iter().enumerate() {
+            if j > 0 {
+                factorial = factorial.checked_mul(j as i128).ok_or(SeriesError::Overflow)?;
+                let mut next = vec![0; falling.len() + 1];
+                for (power, coefficient) in falling.iter().enumerate() {
+                    next[power + 1] += coefficient;
+                    next[power] -= coefficient.checked_mul(j as i128 - 1).ok_or(SeriesError::Overflow)?;
+                }
+                falling = next;
+            }
+
+            let scale = Rational::new(*difference, factorial)?;
+            for (power, coefficient) in falling.iter().enumerate() {
+                res[power] = res[power].checked_add(scale.checked_mul(Rational::integer(*coefficient))?)?;
+            }
         }
 
-        (left, right)
+        Ok(res)
     }
 }
 
 fn main() {
-    let lines = read_lines_from_file("day9.input");
-    let series = lines.iter().map(Series::new).collect::<Vec<_>>();
+    let lines = read_input(9);
+    let series = lines.iter().map(|line| {Series::new(line)}).collect::<Vec<_>>();
 
     for series in &series {
-        println!("{:?} {:?}", series, series.next())
+        match series.degree().and_then(|degree| {Ok((degree, series.coefficients()?, series.next()?))}) {
+            Ok((degree, coefficients, next)) => {
+                println!("{:?} degree {} coefficients [{}] {:?}", series, degree, coefficients.iter().map(|x| {x.to_string()}).collect::<Vec<_>>().join(", "), next)
+            }
+            Err(err) => println!("{:?} {}", series, err),
+        }
     }
 
-    let left = series.iter().map(|x| {x.next().0}).sum::<i32>();
-    let right = series.iter().map(|x| {x.next().1}).sum::<i32>();
-
-    println!("Left: {}", left);
-    println!("Right: {}", right)
-}
\ No newline at end of file
+    let steps = flag_value("--steps").map(|value| {value.parse::<i64>().unwrap_or_else(|_| {panic!("Cant parse --steps {value}")})}).unwrap_or(1);
+
+    let next = series.iter().map(|series| {series.extrapolate(steps)}).collect::<Result<Vec<_>, _>>();
+    let sums = next.and_then(|next| {
+        let sum = |values: Vec<i64>| {values.into_iter().try_fold(0i64, i64::checked_add).ok_or(SeriesError::Overflow)};
+        Ok((sum(next.iter().map(|x| {x.0}).collect())?, sum(next.iter().map(|x| {x.1}).collect())?))
+    });
+    match sums {
+        Ok((left, right)) => {
+            println!("Left: {}", left);
+            println!("Right: {}", right)
+        }
+        Err(err) => println!("Cant extrapolate {} steps: {}", steps, err),
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Shape of the sequence `start, step(start), step(step(start)), ...`: the first `tail`
+/// states are never revisited, after which states repeat every `period` steps.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Cycle {
+    pub tail: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    /// The smallest number of steps reaching the same state as `n` steps.
+    pub fn equivalent(&self, n: u64) -> u64 {
+        let tail = self.tail as u64;
+        if n < tail {
+            n
+        } else {
+            tail + (n - tail) % self.period as u64
+        }
+    }
+}
+
+/// Floyd's tortoise and hare, using constant memory.
+pub fn floyd<T: Clone + Eq>(start: &T, mut step: impl FnMut(&T) -> T) -> Cycle {
+    let mut tortoise = step(start);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        let next = step(&hare);
+        hare = step(&next);
+    }
+
+    let mut tail = 0;
+    tortoise = start.clone();
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        tail += 1;
+    }
+
+    let mut period = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        period += 1;
+    }
+
+    Cycle { tail, period }
+}
+
+/// Brent's algorithm, using constant memory and fewer steps than Floyd's.
+pub fn brent<T: Clone + Eq>(start: &T, mut step: impl FnMut(&T) -> T) -> Cycle {
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(start);
+    while tortoise != hare {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+        hare = step(&hare);
+        period += 1;
+    }
+
+    let mut tortoise = start.clone();
+    let mut hare = start.clone();
+    for _ in 0..period {
+        hare = step(&hare);
+    }
+
+    let mut tail = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        tail += 1;
+    }
+
+    Cycle { tail, period }
+}
+
+/// Remembers every state seen, stepping each state only once.
+pub fn hashed<T: Clone + Hash + Eq>(start: &T, step: impl FnMut(&T) -> T) -> Cycle {
+    hashed_by(start, step, T::clone)
+}
+
+/// Like [`hashed`], but remembers only a `key` per state, which must identify it uniquely.
+/// Useful when states have a much more compact representation.
+pub fn hashed_by<T, K: Hash + Eq>(start: &T, mut step: impl FnMut(&T) -> T, mut key: impl FnMut(&T) -> K) -> Cycle {
+    let mut seen: HashMap<K, usize> = HashMap::new();
+    seen.insert(key(start), 0);
+    let mut current = step(start);
+    let mut idx = 1;
+
+    loop {
+        let current_key = key(&current);
+        if let Some(first) = seen.get(&current_key) {
+            return Cycle {
+                tail: *first,
+                period: idx - first,
+            };
+        }
+
+        seen.insert(current_key, idx);
+        current = step(&current);
+        idx += 1;
+    }
+}
+
+/// The state after `n` steps, skipping whole periods once the cycle is known.
+pub fn state_after<T: Clone + Eq>(start: &T, mut step: impl FnMut(&T) -> T, n: u64) -> T {
+    let cycle = brent(start, &mut step);
+    advance(start, step, &cycle, n)
+}
+
+/// The state after `n` steps, given the already detected `cycle` of the sequence.
+pub fn advance<T: Clone>(start: &T, mut step: impl FnMut(&T) -> T, cycle: &Cycle, n: u64) -> T {
+    let mut res = start.clone();
+    for _ in 0..cycle.equivalent(n) {
+        res = step(&res);
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `0, 1, ..., tail - 1` followed by `tail, ..., tail + period - 1` repeating.
+    fn rho(tail: u64, period: u64) -> impl Fn(&u64) -> u64 {
+        move |x| {
+            if x + 1 < tail + period {
+                x + 1
+            } else {
+                tail
+            }
+        }
+    }
+
+    #[test]
+    fn detectors_agree() {
+        for (tail, period) in [(0, 1), (0, 7), (1, 1), (5, 7), (12, 3), (3, 64), (100, 1)] {
+            let expected = Cycle {
+                tail: tail as usize,
+                period: period as usize,
+            };
+            assert_eq!(floyd(&0, rho(tail, period)), expected, "floyd {tail} {period}");
+            assert_eq!(brent(&0, rho(tail, period)), expected, "brent {tail} {period}");
+            assert_eq!(hashed(&0, rho(tail, period)), expected, "hashed {tail} {period}");
+            assert_eq!(hashed_by(&0, rho(tail, period), |x| {x.to_string()}), expected, "hashed_by {tail} {period}");
+        }
+    }
+
+    #[test]
+    fn detectors_agree_on_quadratic_map() {
+        let step = |x: &u64| {(x * x + 1) % 1009};
+        let cycle = hashed(&2, step);
+        assert_eq!(floyd(&2, step), cycle);
+        assert_eq!(brent(&2, step), cycle);
+        assert_eq!(state_after(&2, step, cycle.tail as u64), state_after(&2, step, (cycle.tail + cycle.period) as u64));
+    }
+
+    #[test]
+    fn equivalent_steps() {
+        let cycle = Cycle { tail: 5, period: 7 };
+        assert_eq!(cycle.equivalent(3), 3);
+        assert_eq!(cycle.equivalent(5), 5);
+        assert_eq!(cycle.equivalent(12), 5);
+        assert_eq!(cycle.equivalent(13), 6);
+        assert_eq!(cycle.equivalent(u64::MAX), 5 + (u64::MAX - 5) % 7);
+    }
+
+    #[test]
+    fn state_after_huge_n() {
+        assert_eq!(state_after(&0, rho(5, 7), 4), 4);
+        assert_eq!(state_after(&0, rho(5, 7), 1_000_000_000_000), 5 + (1_000_000_000_000 - 5) % 7);
+        assert_eq!(state_after(&0, rho(5, 7), u64::MAX), 5 + (u64::MAX - 5) % 7);
+        assert_eq!(state_after(&0, rho(0, 1), u64::MAX), 0);
+    }
+}
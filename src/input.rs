@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+
+/// Which file of a day to read: the puzzle input or one of its examples.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Source {
+    Input,
+    Example(usize),
+}
+
+impl Source {
+    /// `--example N` picks the N-th example, `--example` without a number the first one.
+    pub fn from_args() -> Source {
+        Source::parse(&std::env::args().collect::<Vec<_>>())
+    }
+
+    fn parse(args: &[String]) -> Source {
+        match args.iter().position(|arg| {arg == "--example"}) {
+            None => Source::Input,
+            Some(idx) => match value(args, idx) {
+                None => Source::Example(1),
+                Some(value) => Source::Example(value.parse().unwrap_or_else(|_| {panic!("Cant parse --example {value}")})),
+            },
+        }
+    }
+
+    /// File names this source may go by, in order of preference. Examples are either
+    /// numbered (`day10.example.3`) or split by puzzle part (`day1part2.example`).
+    fn candidates(&self, day: u32) -> Vec<String> {
+        match self {
+            Source::Input => vec![format!("day{day}.input")],
+            Source::Example(n) => {
+                let mut res = vec![format!("day{day}.example.{n}"), format!("day{day}part{n}.example")];
+                if *n == 1 {
+                    res.push(format!("day{day}.example"));
+                }
+                res
+            }
+        }
+    }
+}
+
+/// Values following every occurrence of the flag `name`, e.g. `--score horizontal=2 --score vertical=3`.
+pub fn flag_values(name: &str) -> Vec<String> {
+    values(&std::env::args().collect::<Vec<_>>(), name)
+}
+
+/// Value following the flag `name`, the last one if it is repeated.
+pub fn flag_value(name: &str) -> Option<String> {
+    flag_values(name).pop()
+}
+
+fn values(args: &[String], name: &str) -> Vec<String> {
+    args.iter().enumerate().filter(|(_, arg)| {*arg == name}).map(|(idx, _)| {
+        value(args, idx).cloned().unwrap_or_else(|| {panic!("Cant parse {name}, expected a value after it")})
+    }).collect()
+}
+
+/// The argument after the flag at `idx`, unless that is another flag.
+fn value(args: &[String], idx: usize) -> Option<&String> {
+    args.get(idx + 1).filter(|arg| {!arg.starts_with("--")})
+}
+
+/// Directory holding the puzzle files: `AOC_INPUT_DIR` if set, the crate root otherwise.
+pub fn input_dir() -> PathBuf {
+    match std::env::var_os("AOC_INPUT_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env!("CARGO_MANIFEST_DIR")),
+    }
+}
+
+pub fn resolve(day: u32, source: &Source) -> PathBuf {
+    let dir = input_dir();
+    let candidates = source.candidates(day);
+    candidates.iter().map(|name| {dir.join(name)}).find(|path| {path.is_file()})
+        .unwrap_or_else(|| {panic!("No {:?} for day {} in {}, tried {}", source, day, dir.display(), candidates.join(", "))})
+}
+
+/// Path of the file selected on the command line for `day`.
+pub fn input_path(day: u32) -> PathBuf {
+    resolve(day, &Source::from_args())
+}
+
+/// Lines of the file selected on the command line for `day`.
+pub fn read_input(day: u32) -> Vec<String> {
+    crate::read_lines_from_file(input_path(day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(String::from).collect()
+    }
+
+    #[test]
+    fn example_source() {
+        assert_eq!(Source::parse(&args("day10")), Source::Input);
+        assert_eq!(Source::parse(&args("day10 --example")), Source::Example(1));
+        assert_eq!(Source::parse(&args("day10 --example 3")), Source::Example(3));
+        assert_eq!(Source::parse(&args("day10 --render svg --example 2")), Source::Example(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cant parse --example foo")]
+    fn example_source_rejects_words() {
+        Source::parse(&args("day10 --example foo"));
+    }
+
+    #[test]
+    fn example_source_before_flags() {
+        assert_eq!(Source::parse(&args("day10 --example --render svg")), Source::Example(1));
+        assert_eq!(Source::parse(&args("day12 --example --unfold 3")), Source::Example(1));
+    }
+
+    #[test]
+    fn flag_values_in_order() {
+        assert_eq!(values(&args("day13 --score horizontal=2 --example 1 --score vertical=3"), "--score"), vec!["horizontal=2", "vertical=3"]);
+        assert!(values(&args("day13 --example"), "--score").is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cant parse --render, expected a value after it")]
+    fn flag_without_value() {
+        values(&args("day10 --render"), "--render");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cant parse --export, expected a value after it")]
+    fn flag_followed_by_flag() {
+        values(&args("day8 --export --example 1"), "--export");
+    }
+
+    #[test]
+    fn negative_flag_value() {
+        assert_eq!(values(&args("day9 --steps -3"), "--steps"), vec!["-3"]);
+    }
+
+    #[test]
+    fn candidate_names() {
+        assert_eq!(Source::Input.candidates(8), vec!["day8.input"]);
+        assert_eq!(Source::Example(1).candidates(1), vec!["day1.example.1", "day1part1.example", "day1.example"]);
+        assert_eq!(Source::Example(2).candidates(1), vec!["day1.example.2", "day1part2.example"]);
+    }
+}
@@ -1,6 +1,12 @@
-pub fn read_lines_from_file(filename: &str) -> Vec<String> {
-    match std::fs::read_to_string(filename) {
+use std::path::Path;
+
+pub mod cycle;
+pub mod input;
+pub mod math;
+
+pub fn read_lines_from_file(filename: impl AsRef<Path>) -> Vec<String> {
+    match std::fs::read_to_string(filename.as_ref()) {
         Ok(contents)=> contents.lines().map(|x| {x.to_owned()}).collect(),
-        _ => panic!("File {} not found!", filename)
+        _ => panic!("File {} not found!", filename.as_ref().display())
     }
-}
\ No newline at end of file
+}
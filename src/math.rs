@@ -0,0 +1,327 @@
+use std::fmt::{Debug, Display, Formatter};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MathError {
+    Overflow,
+    ZeroModulus,
+    DivisionByZero,
+    NotInvertible,
+    Inconsistent,
+}
+
+impl Display for MathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MathError::Overflow => write!(f, "arithmetic overflow"),
+            MathError::ZeroModulus => write!(f, "modulus must be positive"),
+            MathError::DivisionByZero => write!(f, "division by zero"),
+            MathError::NotInvertible => write!(f, "value has no modular inverse"),
+            MathError::Inconsistent => write!(f, "congruences have no common solution"),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
+/// Primitive integer operations needed by the helpers in this module.
+pub trait Integer: Copy + Ord + Debug {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+    fn checked_rem(self, rhs: Self) -> Option<Self>;
+    fn checked_abs(self) -> Option<Self>;
+}
+
+/// Integers with negative values, required for Bézout coefficients.
+pub trait Signed: Integer {
+    fn checked_neg(self) -> Option<Self>;
+}
+
+macro_rules! integer {
+    ($($t:ty),*) => {
+        $(impl Integer for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn checked_add(self, rhs: Self) -> Option<Self> { <$t>::checked_add(self, rhs) }
+            fn checked_sub(self, rhs: Self) -> Option<Self> { <$t>::checked_sub(self, rhs) }
+            fn checked_mul(self, rhs: Self) -> Option<Self> { <$t>::checked_mul(self, rhs) }
+            fn checked_div(self, rhs: Self) -> Option<Self> { <$t>::checked_div(self, rhs) }
+            fn checked_rem(self, rhs: Self) -> Option<Self> { <$t>::checked_rem(self, rhs) }
+            #[allow(unused_comparisons)]
+            fn checked_abs(self) -> Option<Self> { if self < 0 { <$t>::checked_sub(0, self) } else { Some(self) } }
+        })*
+    };
+}
+
+macro_rules! signed {
+    ($($t:ty),*) => {
+        $(impl Signed for $t {
+            fn checked_neg(self) -> Option<Self> { <$t>::checked_neg(self) }
+        })*
+    };
+}
+
+integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+signed!(i8, i16, i32, i64, i128, isize);
+
+fn add<T: Integer>(a: T, b: T) -> Result<T, MathError> {
+    a.checked_add(b).ok_or(MathError::Overflow)
+}
+
+fn sub<T: Integer>(a: T, b: T) -> Result<T, MathError> {
+    a.checked_sub(b).ok_or(MathError::Overflow)
+}
+
+fn mul<T: Integer>(a: T, b: T) -> Result<T, MathError> {
+    a.checked_mul(b).ok_or(MathError::Overflow)
+}
+
+fn div<T: Integer>(a: T, b: T) -> Result<T, MathError> {
+    a.checked_div(b).ok_or(MathError::Overflow)
+}
+
+/// Non-negative remainder of `a` modulo a positive `m`.
+pub fn modulo<T: Integer>(a: T, m: T) -> Result<T, MathError> {
+    if m <= T::ZERO {
+        return Err(MathError::ZeroModulus);
+    }
+
+    let r = a.checked_rem(m).ok_or(MathError::Overflow)?;
+    if r < T::ZERO {
+        add(r, m)
+    } else {
+        Ok(r)
+    }
+}
+
+/// Greatest common divisor, always non-negative. `gcd(0, 0)` is 0.
+pub fn gcd<T: Integer>(a: T, b: T) -> Result<T, MathError> {
+    let mut a = a.checked_abs().ok_or(MathError::Overflow)?;
+    let mut b = b.checked_abs().ok_or(MathError::Overflow)?;
+
+    while b != T::ZERO {
+        let r = a.checked_rem(b).ok_or(MathError::Overflow)?;
+        a = b;
+        b = r;
+    }
+
+    Ok(a)
+}
+
+/// Least common multiple, always non-negative. `lcm(0, x)` is 0.
+pub fn lcm<T: Integer>(a: T, b: T) -> Result<T, MathError> {
+    if a == T::ZERO || b == T::ZERO {
+        return Ok(T::ZERO);
+    }
+
+    let a = a.checked_abs().ok_or(MathError::Overflow)?;
+    let b = b.checked_abs().ok_or(MathError::Overflow)?;
+    mul(div(a, gcd(a, b)?)?, b)
+}
+
+/// Returns `(g, x, y)` with `g = gcd(a, b)` and `a * x + b * y = g`.
+pub fn extended_gcd<T: Signed>(a: T, b: T) -> Result<(T, T, T), MathError> {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (T::ONE, T::ZERO);
+    let (mut old_y, mut y) = (T::ZERO, T::ONE);
+
+    while r != T::ZERO {
+        let q = div(old_r, r)?;
+        (old_r, r) = (r, sub(old_r, mul(q, r)?)?);
+        (old_x, x) = (x, sub(old_x, mul(q, x)?)?);
+        (old_y, y) = (y, sub(old_y, mul(q, y)?)?);
+    }
+
+    if old_r < T::ZERO {
+        let neg = |v: T| {v.checked_neg().ok_or(MathError::Overflow)};
+        Ok((neg(old_r)?, neg(old_x)?, neg(old_y)?))
+    } else {
+        Ok((old_r, old_x, old_y))
+    }
+}
+
+/// Inverse of `a` modulo `m`, in `0..m`.
+pub fn mod_inverse<T: Signed>(a: T, m: T) -> Result<T, MathError> {
+    let a = modulo(a, m)?;
+    let (g, x, _) = extended_gcd(a, m)?;
+    if g != T::ONE {
+        return Err(MathError::NotInvertible);
+    }
+
+    modulo(x, m)
+}
+
+/// Solves the system `x = residue (mod modulus)` for every pair, where moduli need not be
+/// coprime. Returns `(x, m)` with `x` in `0..m` describing every solution `x + k * m`.
+pub fn crt<T: Signed>(congruences: &[(T, T)]) -> Result<(T, T), MathError> {
+    let mut acc = (T::ZERO, T::ONE);
+
+    for &(residue, modulus) in congruences {
+        let residue = modulo(residue, modulus)?;
+        let (a1, m1) = acc;
+        let (g, p, _) = extended_gcd(m1, modulus)?;
+
+        let diff = sub(residue, a1)?;
+        if modulo(diff, g)? != T::ZERO {
+            return Err(MathError::Inconsistent);
+        }
+
+        let reduced = div(modulus, g)?;
+        let k = modulo(mul(modulo(div(diff, g)?, reduced)?, modulo(p, reduced)?)?, reduced)?;
+        let m = mul(div(m1, g)?, modulus)?;
+        acc = (modulo(add(a1, mul(m1, k)?)?, m)?, m);
+    }
+
+    Ok(acc)
+}
+
+/// Exact fraction kept in lowest terms with a positive denominator.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    pub fn new(numerator: i128, denominator: i128) -> Result<Rational, MathError> {
+        if denominator == 0 {
+            return Err(MathError::DivisionByZero);
+        }
+
+        let g = gcd(numerator, denominator)?;
+        let sign = if denominator < 0 { -1 } else { 1 };
+        Ok(Rational {
+            numerator: mul(numerator / g, sign)?,
+            denominator: mul(denominator / g, sign)?,
+        })
+    }
+
+    pub fn integer(value: i128) -> Rational {
+        Rational {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    pub fn numerator(&self) -> i128 {
+        self.numerator
+    }
+
+    pub fn denominator(&self) -> i128 {
+        self.denominator
+    }
+
+    pub fn checked_add(self, rhs: Rational) -> Result<Rational, MathError> {
+        let denominator = lcm(self.denominator, rhs.denominator)?;
+        let left = mul(self.numerator, denominator / self.denominator)?;
+        let right = mul(rhs.numerator, denominator / rhs.denominator)?;
+        Rational::new(add(left, right)?, denominator)
+    }
+
+    pub fn checked_mul(self, rhs: Rational) -> Result<Rational, MathError> {
+        let first = gcd(self.numerator, rhs.denominator)?.max(1);
+        let second = gcd(rhs.numerator, self.denominator)?.max(1);
+        Rational::new(
+            mul(self.numerator / first, rhs.numerator / second)?,
+            mul(self.denominator / second, rhs.denominator / first)?,
+        )
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.denominator {
+            1 => write!(f, "{}", self.numerator),
+            _ => write!(f, "{}/{}", self.numerator, self.denominator),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modulo_is_non_negative() {
+        assert_eq!(modulo(7, 3), Ok(1));
+        assert_eq!(modulo(-7, 3), Ok(2));
+        assert_eq!(modulo(-6i64, 3), Ok(0));
+        assert_eq!(modulo(5, 0), Err(MathError::ZeroModulus));
+        assert_eq!(modulo(5, -3), Err(MathError::ZeroModulus));
+    }
+
+    #[test]
+    fn gcd_of_signed_and_unsigned() {
+        assert_eq!(gcd(12, 18), Ok(6));
+        assert_eq!(gcd(-12, 18), Ok(6));
+        assert_eq!(gcd(12u64, 0), Ok(12));
+        assert_eq!(gcd(0, 0), Ok(0));
+        assert_eq!(gcd(i64::MIN, 2), Err(MathError::Overflow));
+        assert_eq!(gcd(2, i64::MIN), Err(MathError::Overflow));
+    }
+
+    #[test]
+    fn lcm_of_signed_and_unsigned() {
+        assert_eq!(lcm(4, 6), Ok(12));
+        assert_eq!(lcm(-4, 6), Ok(12));
+        assert_eq!(lcm(0, 6), Ok(0));
+        assert_eq!(lcm(21u8, 6), Ok(42));
+        assert_eq!(lcm(i64::MAX, i64::MAX - 1), Err(MathError::Overflow));
+    }
+
+    #[test]
+    fn extended_gcd_satisfies_bezout() {
+        for (a, b) in [(240i64, 46), (-240, 46), (240, -46), (-240, -46), (0, 5), (5, 0), (-7, 0)] {
+            let (g, x, y) = extended_gcd(a, b).unwrap();
+            assert_eq!(g, gcd(a, b).unwrap());
+            assert_eq!(a * x + b * y, g);
+        }
+    }
+
+    #[test]
+    fn mod_inverse_in_range() {
+        assert_eq!(mod_inverse(3, 11), Ok(4));
+        assert_eq!(mod_inverse(-3, 11), Ok(7));
+        assert_eq!(mod_inverse(4, 6), Err(MathError::NotInvertible));
+        assert_eq!(mod_inverse(0, 7), Err(MathError::NotInvertible));
+        assert_eq!(mod_inverse(3, 0), Err(MathError::ZeroModulus));
+    }
+
+    #[test]
+    fn crt_with_coprime_moduli() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Ok((23, 105)));
+        assert_eq!(crt(&[(-1, 3), (-1, 5)]), Ok((14, 15)));
+        assert_eq!(crt::<i64>(&[]), Ok((0, 1)));
+    }
+
+    #[test]
+    fn crt_with_non_coprime_moduli() {
+        assert_eq!(crt(&[(2, 4), (4, 6)]), Ok((10, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), Err(MathError::Inconsistent));
+        assert_eq!(crt(&[(3, 6), (3, 6)]), Ok((3, 6)));
+        assert_eq!(crt(&[(1, 0)]), Err(MathError::ZeroModulus));
+    }
+
+    #[test]
+    fn crt_overflow() {
+        assert_eq!(crt(&[(0i64, i64::MAX), (1, i64::MAX - 1)]), Err(MathError::Overflow));
+    }
+
+    #[test]
+    fn rational_in_lowest_terms() {
+        let half = Rational::new(-2, -4).unwrap();
+        assert_eq!((half.numerator(), half.denominator()), (1, 2));
+        assert_eq!(Rational::new(3, -6).unwrap().to_string(), "-1/2");
+        assert_eq!(Rational::new(1, 0), Err(MathError::DivisionByZero));
+
+        let third = Rational::new(1, 3).unwrap();
+        assert_eq!(half.checked_add(third), Rational::new(5, 6));
+        assert_eq!(half.checked_mul(Rational::integer(4)), Ok(Rational::integer(2)));
+        assert_eq!(Rational::integer(i128::MAX).checked_add(Rational::integer(1)), Err(MathError::Overflow));
+    }
+}